@@ -0,0 +1,50 @@
+//! A blocking facade over the async API, for callers that don't run an async
+//! runtime. Each call starts a short lived runtime of its own, so these
+//! functions panic if called from within one.
+
+use std::path::Path;
+
+use crate::{
+    error::{self, Error},
+    InputFormat, PodGraph, PodKind, RenderOptions,
+};
+
+pub(crate) fn block_on<F: std::future::Future>(f: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .expect("failed to start a tokio runtime")
+        .block_on(f)
+}
+
+/// Reads the decklist at `path` and builds its graph, resolving the cards
+/// through Scryfall. Fails with [Error::Unresolved] if any line can't be
+/// resolved.
+///
+/// ```no_run
+/// # fn f() -> pyre::error::Result<()> {
+/// use pyre::{PodGraph, PyreOfHeroes};
+///
+/// let graph: PodGraph<PyreOfHeroes> = pyre::blocking::build_graph("deck.txt")?;
+/// pyre::blocking::to_img(&graph, "graph.dot").map_err(pyre::Error::Output)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn build_graph<K: PodKind + Default>(path: impl AsRef<Path>) -> error::Result<PodGraph<K>> {
+    block_on(async {
+        let decklist = tokio::fs::File::open(path).await.map_err(Error::Input)?;
+        Ok(PodGraph::<K>::builder()
+            .add_decklist(decklist, InputFormat::Auto)
+            .await?
+            .build())
+    })
+}
+
+/// Writes the graph as graphviz source to `path`. See [PodGraph::to_img].
+pub fn to_img<K: PodKind>(
+    graph: &PodGraph<K>,
+    path: impl AsRef<Path>,
+    options: &RenderOptions,
+) -> std::io::Result<()> {
+    block_on(graph.to_img(path, options))
+}
@@ -0,0 +1,157 @@
+use tokio::io::AsyncRead;
+
+use crate::{
+    decklist::{self, InputFormat},
+    error::{self, Error},
+    source::{CardSource, Scryfall},
+    BirthingPod, Card, Collection, Metadata, PodGraph, PodKind,
+};
+
+/// Builds a [PodGraph] out of cards or decklists.
+///
+/// ```no_run
+/// # async fn f() -> pyre::error::Result<()> {
+/// use pyre::{InputFormat, PodGraphBuilder, PyreOfHeroes, RenderOptions};
+///
+/// let decklist = "1 Reveillark\n1 Restoration Angel\n1 Kitchen Finks\n";
+/// let graph = PodGraphBuilder::new()
+///     .with_kind(PyreOfHeroes::default())
+///     .add_decklist(decklist.as_bytes(), InputFormat::Auto)
+///     .await?
+///     .highlight("Reveillark")
+///     .build();
+/// println!("{}", graph.to_dot(&RenderOptions::default()).await);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct PodGraphBuilder<K> {
+    kind: K,
+    cards: Vec<Card>,
+    highlight: Option<String>,
+    highlight_chain: Option<Vec<String>>,
+    owned: Option<Collection>,
+    metadata: Option<Metadata>,
+}
+
+impl Default for PodGraphBuilder<BirthingPod> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PodGraphBuilder<BirthingPod> {
+    /// Starts building an empty [BirthingPod] graph. Use
+    /// [with_kind](Self::with_kind) to pick another engine.
+    pub fn new() -> Self {
+        Self::for_kind(BirthingPod)
+    }
+}
+
+impl<K> PodGraphBuilder<K> {
+    pub(crate) fn for_kind(kind: K) -> Self {
+        Self {
+            kind,
+            cards: Vec::new(),
+            highlight: None,
+            highlight_chain: None,
+            owned: None,
+            metadata: None,
+        }
+    }
+}
+
+impl<K: PodKind> PodGraphBuilder<K> {
+    /// Changes the engine the graph is built for.
+    pub fn with_kind<K2: PodKind>(self, kind: K2) -> PodGraphBuilder<K2> {
+        PodGraphBuilder {
+            kind,
+            cards: self.cards,
+            highlight: self.highlight,
+            highlight_chain: self.highlight_chain,
+            owned: self.owned,
+            metadata: self.metadata,
+        }
+    }
+
+    /// Adds already resolved cards.
+    pub fn add_cards(mut self, cards: impl IntoIterator<Item = Card>) -> Self {
+        self.cards.extend(cards);
+        self
+    }
+
+    /// Resolves and adds the creatures of a decklist through [Scryfall]. Fails
+    /// with [Error::Unresolved] if any line can't be resolved.
+    pub async fn add_decklist<R: AsyncRead>(
+        self,
+        r: R,
+        format: InputFormat,
+    ) -> error::Result<Self> {
+        self.add_decklist_from(r, Scryfall, format).await
+    }
+
+    /// Like [add_decklist](Self::add_decklist), but resolving the cards
+    /// through `source`.
+    pub async fn add_decklist_from<R: AsyncRead, S: CardSource + Clone>(
+        self,
+        r: R,
+        source: S,
+        format: InputFormat,
+    ) -> error::Result<Self> {
+        let (cards, failed) = decklist::collect(
+            decklist::parse(
+                r,
+                source,
+                format,
+                decklist::DEFAULT_CONCURRENCY,
+                (),
+                false,
+                decklist::into_creature,
+            )
+            .await,
+        )
+        .await?;
+        if failed.is_empty() {
+            Ok(self.add_cards(cards))
+        } else {
+            Err(Error::Unresolved(failed))
+        }
+    }
+
+    /// Highlights the cards that can reach the named card when rendering.
+    pub fn highlight(mut self, name: impl Into<String>) -> Self {
+        self.highlight = Some(name.into());
+        self
+    }
+
+    /// Highlights only the named cards when rendering, instead of the cards
+    /// that can reach the [highlighted](Self::highlight) one.
+    pub fn highlight_chain(mut self, names: impl IntoIterator<Item = String>) -> Self {
+        self.highlight_chain = Some(names.into_iter().collect());
+        self
+    }
+
+    /// Dashes the cards missing from the collection when rendering.
+    pub fn owned(mut self, collection: Collection) -> Self {
+        self.owned = Some(collection);
+        self
+    }
+
+    /// Records where the graph came from, to be written in its outputs.
+    pub fn metadata(mut self, metadata: Metadata) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
+
+    /// Builds the graph, linking the cards in parallel. See
+    /// [PodGraph::add_cards].
+    pub fn build(self) -> PodGraph<K> {
+        let mut graph = PodGraph::with_kind(self.kind);
+        graph.add_cards(self.cards);
+        graph.set_highlight(self.highlight);
+        graph.set_highlight_chain(self.highlight_chain);
+        graph.set_owned(self.owned);
+        graph.set_metadata(self.metadata);
+        graph
+    }
+}
@@ -0,0 +1,57 @@
+pub(crate) mod bookmarks;
+pub(crate) mod census;
+pub(crate) mod check;
+pub(crate) mod checkpoint;
+#[cfg(feature = "clipboard")]
+pub(crate) mod clipboard;
+pub(crate) mod cube;
+pub(crate) mod engine;
+pub(crate) mod events;
+pub(crate) mod fixes;
+pub(crate) mod graph_cache;
+pub(crate) mod graphviz;
+pub(crate) mod hands;
+pub(crate) mod history;
+pub(crate) mod logging;
+pub(crate) mod markdown;
+pub(crate) mod progress;
+pub(crate) mod proxies;
+pub(crate) mod report;
+pub(crate) mod require;
+pub(crate) mod resolved;
+pub(crate) mod serve;
+pub(crate) mod stamp;
+pub(crate) mod watch;
+
+use std::process::ExitCode;
+
+use pyre::{error::Error, FailedLine};
+
+mod exit_code {
+    pub const CARD_NOT_FOUND: u8 = 3;
+    pub const NETWORK: u8 = 4;
+    pub const INVALID_DECKLIST: u8 = 5;
+    pub const OUTPUT: u8 = 6;
+    pub const NO_CREATURES: u8 = 7;
+}
+
+pub(crate) const EXIT_CODES_HELP: &str = "\
+Exit codes:
+  1  the decklist failed --check
+  3  a card could not be found
+  4  a network error prevented resolving a card
+  5  the decklist could not be read
+  6  the output could not be written
+  7  no creatures were left to graph";
+
+pub(crate) fn exit_code(e: &Error) -> ExitCode {
+    ExitCode::from(match e {
+        Error::Line(l) if l.is_network() => exit_code::NETWORK,
+        Error::Line(_) => exit_code::CARD_NOT_FOUND,
+        Error::Unresolved(lines) if lines.iter().any(FailedLine::is_network) => exit_code::NETWORK,
+        Error::Unresolved(_) => exit_code::CARD_NOT_FOUND,
+        Error::Input(_) => exit_code::INVALID_DECKLIST,
+        Error::Output(_) => exit_code::OUTPUT,
+        Error::NoCreatures { .. } => exit_code::NO_CREATURES,
+    })
+}
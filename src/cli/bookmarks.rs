@@ -0,0 +1,106 @@
+//! `--save-chain` and `--chain`: chains worth coming back to, saved by name
+//! next to the decklist, like `deck.chains.json` for `deck.txt`.
+
+use std::{
+    collections::BTreeMap,
+    io,
+    path::{Path, PathBuf},
+};
+
+use pyre::{
+    error::{self, Error},
+    PodGraph, PodKind,
+};
+use tracing::{info, warn};
+
+use crate::{cli::proxies, Args};
+
+fn path(deck: &Path) -> PathBuf {
+    deck.with_extension("chains.json")
+}
+
+/// The chains saved for the deck, by name, none if there's no file yet.
+async fn load(path: &Path) -> error::Result<BTreeMap<String, Vec<String>>> {
+    match tokio::fs::read(path).await {
+        Ok(json) => serde_json::from_slice(&json).map_err(|e| {
+            Error::Input(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {e}", path.display()),
+            ))
+        }),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+        Err(e) => Err(Error::Input(e)),
+    }
+}
+
+/// Saves the [chain](proxies::chain) the graph would be printed for under
+/// `name`, replacing the chain saved under it before, if any.
+async fn save<K: PodKind>(graph: &PodGraph<K>, name: &str, deck: &Path) -> error::Result<()> {
+    let chain = proxies::chain(graph)
+        .into_iter()
+        .map(|c| c.name.clone())
+        .collect::<Vec<_>>();
+    if chain.is_empty() {
+        return Err(Error::Input(io::Error::other(format!(
+            "there's no chain to save as {name:?}"
+        ))));
+    }
+    let path = path(deck);
+    let mut chains = load(&path).await?;
+    let saved = chain.join(" -> ");
+    chains.insert(name.to_owned(), chain);
+    let json = serde_json::to_vec_pretty(&chains).expect("chains always serialize");
+    tokio::fs::write(&path, json).await.map_err(Error::Output)?;
+    info!("saved {name:?} to {}: {saved}", path.display());
+    Ok(())
+}
+
+/// Highlights the chain saved under `name`, warning about the cards of it
+/// that aren't in the graph anymore.
+async fn highlight<K: PodKind>(
+    graph: &mut PodGraph<K>,
+    name: &str,
+    deck: &Path,
+) -> error::Result<()> {
+    let path = path(deck);
+    let mut chains = load(&path).await?;
+    let Some(chain) = chains.remove(name) else {
+        let message = match chains.is_empty() {
+            true => format!("no chains are saved in {}", path.display()),
+            false => format!(
+                "no chain named {name:?} in {}, the saved ones are {}",
+                path.display(),
+                chains.keys().cloned().collect::<Vec<_>>().join(", ")
+            ),
+        };
+        return Err(Error::Input(io::Error::other(message)));
+    };
+    let missing = chain
+        .iter()
+        .filter(|c| graph.cards().all(|card| card.name != **c))
+        .map(String::as_str)
+        .collect::<Vec<_>>();
+    if !missing.is_empty() {
+        warn!(
+            "{name:?} isn't whole anymore, the deck has no {}",
+            missing.join(", ")
+        );
+    }
+    graph.set_highlight_chain(Some(chain));
+    Ok(())
+}
+
+/// Saves the chain with `--save-chain` or highlights the one named by
+/// `--chain`, in the sidecar file of the decklist.
+pub(crate) async fn apply<K: PodKind>(graph: &mut PodGraph<K>, args: &Args) -> error::Result<()> {
+    let Some(deck) = &args.file else {
+        return Ok(());
+    };
+    if let Some(name) = &args.save_chain {
+        save(graph, name, deck).await?;
+    }
+    if let Some(name) = &args.chain {
+        highlight(graph, name, deck).await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,60 @@
+//! `--type-census`: which creature types a Pyre of Heroes deck can actually
+//! climb with.
+
+use std::collections::BTreeSet;
+
+use pyre::{PodGraph, PodKind};
+
+/// Prints how many creatures of each type there are at each mana value,
+/// flagging the types that can never chain: those with no two cards a mana
+/// value apart, counting the cards with every creature type as any type.
+pub(crate) fn print<K: PodKind>(graph: &PodGraph<K>) {
+    let census = graph.type_census();
+    let every_type = graph
+        .cards()
+        .filter(|c| c.is_every_creature_type())
+        .map(|c| c.cmc)
+        .collect::<BTreeSet<_>>();
+    let cmcs = graph.cards().map(|c| c.cmc).collect::<BTreeSet<_>>();
+    let width = census
+        .keys()
+        .map(|t| t.chars().count())
+        .max()
+        .unwrap_or(0)
+        .max("type".len());
+    print!("{:width$}", "type");
+    for cmc in &cmcs {
+        print!(" {cmc:>3}");
+    }
+    println!(" total");
+    for (ty, by_cmc) in &census {
+        print!("{ty:width$}");
+        for cmc in &cmcs {
+            match by_cmc.get(cmc) {
+                Some(count) => print!(" {count:>3}"),
+                None => print!("   ."),
+            }
+        }
+        print!(" {:>5}", by_cmc.values().sum::<usize>());
+        let chains = by_cmc.keys().any(|cmc| {
+            [cmc.checked_sub(1), cmc.checked_add(1)]
+                .into_iter()
+                .flatten()
+                .any(|n| by_cmc.contains_key(&n) || every_type.contains(&n))
+        });
+        if !chains {
+            match by_cmc.len() {
+                1 => print!("  only at one mana value, never chains"),
+                _ => print!("  no two a mana value apart, never chains"),
+            }
+        }
+        println!();
+    }
+    if !every_type.is_empty() {
+        let cmcs = every_type.iter().map(u8::to_string).collect::<Vec<_>>();
+        println!(
+            "every creature type at mana value {}, counted as any type",
+            cmcs.join(", ")
+        );
+    }
+}
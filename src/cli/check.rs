@@ -0,0 +1,175 @@
+use std::sync::Arc;
+
+use futures::TryStreamExt;
+use scryfall::{
+    card::{Color, Legality},
+    format::Format,
+};
+
+use pyre::{
+    decklist::{self, Line},
+    error::{self, Error},
+    Card,
+};
+
+use crate::{
+    cli::{engine, progress::Progress},
+    open_reader, Args, Source,
+};
+
+#[derive(Debug, Default)]
+struct Report {
+    resolved: usize,
+    not_found: Vec<(String, scryfall::Error)>,
+    fuzzy_matched: Vec<(String, String)>,
+    missing_data: Vec<(String, String)>,
+    non_creatures: Vec<String>,
+    illegal: Vec<(String, Legality)>,
+    /// The combined color identity of the commanders, if there are any.
+    identity: Option<Vec<Color>>,
+    identities: Vec<(String, Vec<Color>)>,
+    /// The engine cards of the pod kind in the decklist.
+    engines: Vec<String>,
+}
+
+impl Report {
+    fn add(&mut self, line: Line, card: scryfall::Result<Card>, args: &Args) {
+        let card = match card {
+            Ok(card) => card,
+            Err(scryfall::Error::Other(what)) => return self.missing_data.push((line.text, what)),
+            Err(e) => return self.not_found.push((line.to_string(), e)),
+        };
+        self.resolved += 1;
+        if engine::is_engine(&engine::cards_for(args.pod, args.engine), &card.name) {
+            self.engines.push(card.name.clone());
+        }
+        if line.commander {
+            let identity = self.identity.get_or_insert_with(Vec::new);
+            for color in &card.color_identity {
+                if !identity.contains(color) {
+                    identity.push(*color);
+                }
+            }
+        } else {
+            self.identities
+                .push((card.name.clone(), card.color_identity.clone()));
+        }
+        if decklist::is_fuzzy_match(&line, &card) {
+            self.fuzzy_matched.push((line.name, card.name.clone()));
+        }
+        let format = args.legal_in;
+        if let Some(format) = format {
+            match card.legalities.get(&format) {
+                Some(Legality::Legal | Legality::Restricted) => {}
+                Some(l) => self.illegal.push((card.name.clone(), *l)),
+                None => self.missing_data.push((
+                    card.name.clone(),
+                    format!("no legality information for {format}"),
+                )),
+            }
+        }
+        if decklist::into_creature(card.clone()).is_none() {
+            self.non_creatures.push(card.name);
+        }
+    }
+
+    /// The cards outside the commanders' color identity.
+    fn off_identity(&self) -> Vec<&str> {
+        let Some(identity) = &self.identity else {
+            return Vec::new();
+        };
+        self.identities
+            .iter()
+            .filter(|(_, colors)| !colors.iter().all(|c| identity.contains(c)))
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+
+    fn is_ok(&self, strict_names: bool) -> bool {
+        self.not_found.is_empty()
+            && self.off_identity().is_empty()
+            && self.missing_data.is_empty()
+            && self.illegal.is_empty()
+            && (!strict_names || self.fuzzy_matched.is_empty())
+    }
+
+    fn print(&self, format: Option<Format>) {
+        println!("resolved {} cards", self.resolved);
+        if !self.not_found.is_empty() {
+            println!("not found:");
+            for (line, e) in &self.not_found {
+                println!("    {line}: {e}");
+            }
+        }
+        if !self.fuzzy_matched.is_empty() {
+            println!("fuzzy matched:");
+            for (line, name) in &self.fuzzy_matched {
+                println!("    {line:?} -> {name:?}");
+            }
+        }
+        if !self.missing_data.is_empty() {
+            println!("missing data:");
+            for (name, what) in &self.missing_data {
+                println!("    {name}: {what}");
+            }
+        }
+        if let Some(format) = format.filter(|_| !self.illegal.is_empty()) {
+            println!("not legal in {format}:");
+            for (name, legality) in &self.illegal {
+                println!("    {name}: {legality:?}");
+            }
+        }
+        let off_identity = self.off_identity();
+        if !off_identity.is_empty() {
+            println!("outside the commanders' color identity:");
+            for name in off_identity {
+                println!("    {name}");
+            }
+        }
+        if !self.non_creatures.is_empty() {
+            println!("dropped non-creatures:");
+            for name in &self.non_creatures {
+                println!("    {name}");
+            }
+        }
+    }
+}
+
+/// Resolves every card of the decklist and reports everything that would be
+/// dropped or fail, without building the graph. Returns whether the decklist
+/// is free of errors.
+pub(crate) async fn check(
+    args: &Args,
+    source: &Source,
+    progress: &Progress,
+) -> error::Result<bool> {
+    let lines = decklist::resolve(
+        open_reader(args).await?,
+        source.clone(),
+        args.input_format(),
+        args.concurrency,
+        progress.clone(),
+    )
+    .await;
+    let file = args
+        .file
+        .as_deref()
+        .filter(|f| f.as_os_str() != "-")
+        .map(|f| Arc::<str>::from(f.to_string_lossy()));
+    let report = lines
+        .try_fold(Report::default(), |mut report, (mut line, card)| {
+            line.file = file.clone();
+            report.add(line, card, args);
+            futures::future::ready(Ok(report))
+        })
+        .await
+        .map_err(Error::Input)?;
+    progress.finish();
+    report.print(args.legal_in);
+    engine::warn_if_missing(
+        args.pod,
+        &engine::cards_for(args.pod, args.engine),
+        &report.engines,
+    );
+    Ok(report.is_ok(args.strict_names))
+}
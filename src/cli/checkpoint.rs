@@ -0,0 +1,106 @@
+//! `--checkpoint`: every card is written down as soon as it's resolved, so a
+//! run that dies halfway picks up where it stopped when run again.
+
+use std::{collections::HashMap, io, path::Path, sync::Arc};
+
+use async_trait::async_trait;
+use pyre::{
+    error::{self, Error},
+    Card, CardSource,
+};
+use serde::{Deserialize, Serialize};
+use tokio::{fs::File, io::AsyncWriteExt, sync::Mutex};
+use tracing::{info, warn};
+
+use crate::Source;
+
+/// One line of the checkpoint file.
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    name: String,
+    card: Card,
+}
+
+/// Resolves names from the checkpoint when it has them, and through the
+/// wrapped source otherwise, appending what it resolves to the checkpoint.
+struct Checkpoint {
+    resolved: HashMap<String, Card>,
+    file: Mutex<File>,
+    inner: Source,
+}
+
+/// Wraps `source` with the checkpoint at `path`, reading back the cards a
+/// previous run already resolved. Lines that can't be read, like the last one
+/// of a run that was killed mid write, are ignored.
+pub(crate) async fn wrap(path: &Path, source: Source) -> error::Result<Source> {
+    let resolved = match tokio::fs::read_to_string(path).await {
+        Ok(text) => text
+            .lines()
+            .filter_map(|l| serde_json::from_str::<Entry>(l).ok())
+            .map(|e| (e.name, e.card))
+            .collect::<HashMap<_, _>>(),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => HashMap::new(),
+        Err(e) => return Err(Error::Input(e)),
+    };
+    if !resolved.is_empty() {
+        info!(
+            "resuming with {} cards from {}",
+            resolved.len(),
+            path.display()
+        );
+    }
+    let file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await
+        .map_err(Error::Output)?;
+    Ok(Arc::new(Checkpoint {
+        resolved,
+        file: Mutex::new(file),
+        inner: source,
+    }))
+}
+
+/// Removes the checkpoint once it's no longer needed.
+pub(crate) async fn finish(path: &Path) {
+    if let Err(e) = tokio::fs::remove_file(path).await {
+        warn!("failed to remove {}: {e}", path.display());
+    }
+}
+
+impl Checkpoint {
+    async fn append(&self, name: &str, card: &Card) -> io::Result<()> {
+        let mut line = serde_json::to_vec(&Entry {
+            name: name.to_owned(),
+            card: card.clone(),
+        })?;
+        line.push(b'\n');
+        let mut file = self.file.lock().await;
+        file.write_all(&line).await?;
+        file.flush().await
+    }
+}
+
+#[async_trait]
+impl CardSource for Checkpoint {
+    async fn resolve(&self, name: &str) -> scryfall::Result<Card> {
+        if let Some(card) = self.resolved.get(name) {
+            return Ok(card.clone());
+        }
+        let card = self.inner.resolve(name).await?;
+        if let Err(e) = self.append(name, &card).await {
+            warn!("failed to write checkpoint: {e}");
+        }
+        Ok(card)
+    }
+
+    async fn prefetch(&self, names: &[&str]) {
+        let missing = names
+            .iter()
+            .copied()
+            .filter(|n| !self.resolved.contains_key(*n))
+            .collect::<Vec<_>>();
+        self.inner.prefetch(&missing).await
+    }
+}
@@ -0,0 +1,80 @@
+//! `--from-clipboard` and `--to-clipboard`, through the platform's clipboard
+//! tools: `pbpaste`/`pbcopy` on macOS, powershell and `clip` on Windows, and
+//! `wl-paste`/`wl-copy` or `xclip` elsewhere.
+
+use std::{io, path::Path, process::Stdio};
+
+use tokio::{io::AsyncWriteExt, process::Command};
+
+fn wayland() -> bool {
+    std::env::var_os("WAYLAND_DISPLAY").is_some()
+}
+
+fn paste_command() -> Command {
+    if cfg!(target_os = "macos") {
+        Command::new("pbpaste")
+    } else if cfg!(windows) {
+        let mut c = Command::new("powershell");
+        c.args(["-NoProfile", "-Command", "Get-Clipboard"]);
+        c
+    } else if wayland() {
+        let mut c = Command::new("wl-paste");
+        c.arg("--no-newline");
+        c
+    } else {
+        let mut c = Command::new("xclip");
+        c.args(["-selection", "clipboard", "-out"]);
+        c
+    }
+}
+
+fn copy_command() -> Command {
+    if cfg!(target_os = "macos") {
+        Command::new("pbcopy")
+    } else if cfg!(windows) {
+        Command::new("clip")
+    } else if wayland() {
+        Command::new("wl-copy")
+    } else {
+        let mut c = Command::new("xclip");
+        c.args(["-selection", "clipboard", "-in"]);
+        c
+    }
+}
+
+/// The text on the clipboard.
+pub(crate) async fn paste() -> io::Result<Vec<u8>> {
+    let output = paste_command().stderr(Stdio::inherit()).output().await?;
+    if output.status.success() {
+        Ok(output.stdout)
+    } else {
+        Err(io::Error::other(format!(
+            "reading the clipboard exited with {}",
+            output.status
+        )))
+    }
+}
+
+/// Puts the contents of the file at `path` on the clipboard. Only text can
+/// be copied, so rendered images are rejected.
+pub(crate) async fn copy_file(path: &Path) -> io::Result<()> {
+    let text = tokio::fs::read(path).await?;
+    if std::str::from_utf8(&text).is_err() {
+        return Err(io::Error::other(format!(
+            "{} isn't text, only text can be copied",
+            path.display()
+        )));
+    }
+    let mut child = copy_command().stdin(Stdio::piped()).spawn()?;
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    stdin.write_all(&text).await?;
+    drop(stdin);
+    let status = child.wait().await?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!(
+            "writing the clipboard exited with {status}"
+        )))
+    }
+}
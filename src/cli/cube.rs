@@ -0,0 +1,179 @@
+//! `cube`: how poddable a cube is, judged from random draft pools.
+
+use std::{collections::BTreeMap, path::Path, time::SystemTime};
+
+use pyre::{
+    decklist,
+    error::{self, Error},
+    hash, Card, InputFormat, PodGraph, PodKind,
+};
+use tokio::fs::File;
+use tracing::info;
+
+use crate::{cli::progress::Progress, collect_cards, report_skipped, Source};
+
+/// SplitMix64, plenty for picking draft pools.
+pub(crate) struct Rng(u64);
+
+impl Rng {
+    pub(crate) fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        hash::mix(self.0)
+    }
+
+    /// A uniformly random index below `n`.
+    pub(crate) fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+/// The seed given, or one from the clock.
+pub(crate) fn seed_or_now(seed: Option<u64>) -> u64 {
+    seed.unwrap_or_else(|| {
+        SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos() as u64)
+    })
+}
+
+/// What a single draft pool's graph looked like.
+struct Sample {
+    creatures: usize,
+    chain: usize,
+    orphans: usize,
+}
+
+fn sample<K: PodKind + Default>(cube: &mut [Card], size: usize, rng: &mut Rng) -> Sample {
+    // partial Fisher-Yates: the first `size` cards end up a uniform sample
+    for i in 0..size {
+        let j = i + rng.below(cube.len() - i);
+        cube.swap(i, j);
+    }
+    let creatures = cube[..size]
+        .iter()
+        .cloned()
+        .filter_map(decklist::into_creature)
+        .collect::<Vec<_>>();
+    let graph = PodGraph::<K>::builder().add_cards(creatures).build();
+    Sample {
+        creatures: graph.cards().count(),
+        chain: graph.longest_chain().map_or(0, |c| c.len()),
+        orphans: graph.orphans().len(),
+    }
+}
+
+/// The mean share of orphans among the creatures of the pools that had any,
+/// `None` if none did. Pools without creatures have no graph to be orphans
+/// of, they're counted apart.
+fn orphan_rate(samples: &[Sample]) -> Option<f64> {
+    let graphed = samples
+        .iter()
+        .filter(|s| s.creatures > 0)
+        .map(|s| s.orphans as f64 / s.creatures as f64)
+        .collect::<Vec<_>>();
+    (!graphed.is_empty()).then(|| graphed.iter().sum::<f64>() / graphed.len() as f64)
+}
+
+fn print(samples: &[Sample], size: usize) {
+    let n = samples.len() as f64;
+    let mut chains = samples.iter().map(|s| s.chain).collect::<Vec<_>>();
+    chains.sort_unstable();
+    let creatures = samples.iter().map(|s| s.creatures).sum::<usize>() as f64 / n;
+    println!("{} pools of {size} cards", samples.len());
+    println!("creatures per pool: {creatures:.1}");
+    println!(
+        "longest chain: mean {:.2}, median {}, min {}, max {}",
+        chains.iter().sum::<usize>() as f64 / n,
+        chains[chains.len() / 2],
+        chains[0],
+        chains[chains.len() - 1],
+    );
+    match orphan_rate(samples) {
+        Some(rate) => println!("orphan rate: {:.1}%", rate * 100.0),
+        None => println!("orphan rate: no pool had a creature"),
+    }
+    let empty = samples.iter().filter(|s| s.creatures == 0).count();
+    if empty > 0 {
+        println!("pools without creatures: {empty}");
+    }
+    println!("longest chain distribution:");
+    let histogram = chains.iter().fold(BTreeMap::new(), |mut acc, c| {
+        *acc.entry(*c).or_insert(0usize) += 1;
+        acc
+    });
+    for (len, count) in histogram {
+        println!(
+            "    {len:>2}: {count:>4} {}",
+            "#".repeat((count * 50).div_ceil(samples.len()))
+        );
+    }
+}
+
+/// Resolves the cube list once, then builds a graph for each of `samples`
+/// random pools of `size` cards and prints how long their chains get and
+/// how many of their creatures are orphans.
+pub(crate) async fn cube<K: PodKind + Default>(
+    path: &Path,
+    size: usize,
+    samples: usize,
+    seed: Option<u64>,
+    source: &Source,
+    progress: &Progress,
+) -> error::Result<()> {
+    let file = File::open(path).await.map_err(Error::Input)?;
+    let cards = decklist::parse(
+        file,
+        source.clone(),
+        InputFormat::Auto,
+        decklist::DEFAULT_CONCURRENCY,
+        progress.clone(),
+        false,
+        Some,
+    )
+    .await;
+    let cards = decklist::in_file(cards, &path.to_string_lossy());
+    let (mut cube, failed) = collect_cards(Box::pin(cards), progress).await?;
+    report_skipped(failed);
+    if size == 0 || size > cube.len() {
+        return Err(Error::Input(std::io::Error::other(format!(
+            "can't draw pools of {size} cards from a cube of {}",
+            cube.len()
+        ))));
+    }
+    let seed = seed_or_now(seed);
+    info!(seed, "sampling {samples} pools");
+    let mut rng = Rng::new(seed);
+    let samples = (0..samples)
+        .map(|_| sample::<K>(&mut cube, size, &mut rng))
+        .collect::<Vec<_>>();
+    if !samples.is_empty() {
+        print(&samples, size);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool(creatures: usize, orphans: usize) -> Sample {
+        Sample {
+            creatures,
+            chain: 0,
+            orphans,
+        }
+    }
+
+    #[test]
+    fn pools_without_creatures_dont_dilute_the_orphan_rate() {
+        assert_eq!(
+            orphan_rate(&[pool(4, 2), pool(0, 0), pool(2, 2)]),
+            Some(0.75)
+        );
+        assert_eq!(orphan_rate(&[pool(0, 0)]), None);
+    }
+}
@@ -0,0 +1,132 @@
+//! `--engine`: well known tutors, picked by card name, mapped to the pod
+//! kind and `--require` conditions that model them.
+
+use std::str::FromStr;
+
+use clap::ValueEnum;
+use tracing::warn;
+
+use crate::{cli::require::Requirement, Pod};
+
+/// A tutor from the [catalogue](ENGINES).
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Engine {
+    name: &'static str,
+    pub(crate) pod: Pod,
+    /// `--require` conditions on the creatures it can fetch.
+    require: &'static [&'static str],
+}
+
+const ENGINES: [Engine; 9] = [
+    Engine {
+        name: "Birthing Pod",
+        pod: Pod::BirthingPod,
+        require: &[],
+    },
+    Engine {
+        name: "Neoform",
+        pod: Pod::BirthingPod,
+        require: &[],
+    },
+    Engine {
+        name: "Prime Speaker Vannifar",
+        pod: Pod::Vannifar,
+        require: &[],
+    },
+    Engine {
+        name: "Pyre of Heroes",
+        pod: Pod::PyreOfHeroes,
+        require: &[],
+    },
+    Engine {
+        name: "Chord of Calling",
+        pod: Pod::XSpell,
+        require: &[],
+    },
+    Engine {
+        name: "Finale of Devastation",
+        pod: Pod::XSpell,
+        require: &[],
+    },
+    Engine {
+        name: "Green Sun's Zenith",
+        pod: Pod::XSpell,
+        require: &["green"],
+    },
+    Engine {
+        name: "Ranger-Captain of Eos",
+        pod: Pod::XSpell,
+        require: &["mv<=1"],
+    },
+    Engine {
+        name: "Recruiter of the Guard",
+        pod: Pod::XSpell,
+        require: &["toughness<=2"],
+    },
+];
+
+/// Lowercase letters and digits only, so "green suns zenith" finds Green
+/// Sun's Zenith.
+fn normalize(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+impl FromStr for Engine {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = normalize(s);
+        ENGINES
+            .iter()
+            .find(|e| normalize(e.name) == name)
+            .copied()
+            .ok_or_else(|| {
+                let names = ENGINES.iter().map(|e| e.name).collect::<Vec<_>>();
+                format!("unknown engine: {s}, expected one of: {}", names.join(", "))
+            })
+    }
+}
+
+/// The engine cards that drive `pod`: the one picked with `--engine`, or
+/// every catalogued engine of that pod kind. Empty for pod kinds that don't
+/// have an engine card, like transmute.
+pub(crate) fn cards_for(pod: Pod, picked: Option<Engine>) -> Vec<&'static str> {
+    match picked {
+        Some(engine) => vec![engine.name],
+        None => ENGINES
+            .iter()
+            .filter(|e| e.pod == pod)
+            .map(|e| e.name)
+            .collect(),
+    }
+}
+
+/// Whether the card is one of the engine `cards`.
+pub(crate) fn is_engine(cards: &[&str], name: &str) -> bool {
+    let name = normalize(name);
+    cards.iter().any(|c| normalize(c) == name)
+}
+
+/// Warns if the decklist has none of the engine `cards`, which usually means
+/// the wrong `--pod` was picked.
+pub(crate) fn warn_if_missing(pod: Pod, cards: &[&str], found: &[String]) {
+    if !cards.is_empty() && found.is_empty() {
+        let pod = pod.to_possible_value().expect("no pod is skipped");
+        warn!(
+            "building a {} graph, but the decklist has none of: {}",
+            pod.get_name(),
+            cards.join(", ")
+        );
+    }
+}
+
+impl Engine {
+    pub(crate) fn requirements(&self) -> impl Iterator<Item = Requirement> {
+        self.require
+            .iter()
+            .map(|r| r.parse().expect("the catalogue's requirements parse"))
+    }
+}
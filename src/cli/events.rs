@@ -0,0 +1,78 @@
+//! `--emit-events`: newline delimited json on stdout, for frontends that
+//! want to show what's happening as it happens.
+
+use std::{
+    io::{self, Write},
+    path::Path,
+};
+
+use pyre::{Card, PodEdge, PodGraph, PodKind};
+use serde::Serialize;
+
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub(crate) enum Event<'a> {
+    /// A decklist line finished resolving.
+    Resolved { name: &'a str },
+    /// A card is in the graph.
+    CardAdded { name: &'a str, cmc: u8 },
+    /// `from` can fetch `to`.
+    EdgeAdded {
+        from: &'a str,
+        to: &'a str,
+        label: String,
+    },
+    /// What analysing the whole graph found.
+    Analysis {
+        orphans: Vec<&'a str>,
+        /// `None` if the graph has a cycle.
+        longest_chain: Option<Vec<&'a str>>,
+        components: usize,
+        activations_per_turn: usize,
+        /// `None` if the graph has a cycle.
+        turns_to_climb: Option<usize>,
+    },
+    /// The output was written.
+    Written { path: &'a Path },
+    /// The output was already rendered from the same graph, so it was left
+    /// alone.
+    Unchanged { path: &'a Path },
+}
+
+/// Writes the event as a single line. Failures are ignored, like any other
+/// write to a closed stdout would be.
+pub(crate) fn emit(event: &Event) {
+    let mut out = io::stdout().lock();
+    let _ = serde_json::to_writer(&mut out, event);
+    let _ = writeln!(out);
+    let _ = out.flush();
+}
+
+fn names(cards: Vec<&Card>) -> Vec<&str> {
+    cards.into_iter().map(|c| c.name.as_str()).collect()
+}
+
+/// Emits every card and edge of the graph, followed by what analysing it
+/// found.
+pub(crate) fn graph<K: PodKind>(graph: &PodGraph<K>) {
+    for card in graph.cards() {
+        emit(&Event::CardAdded {
+            name: &card.name,
+            cmc: card.cmc,
+        });
+    }
+    for (from, to, edge) in graph.edges() {
+        emit(&Event::EdgeAdded {
+            from: &from.name,
+            to: &to.name,
+            label: edge.label(),
+        });
+    }
+    emit(&Event::Analysis {
+        orphans: names(graph.orphans()),
+        longest_chain: graph.longest_chain().map(names),
+        components: graph.components().len(),
+        activations_per_turn: graph.activations_per_turn(),
+        turns_to_climb: graph.turns_to_climb(),
+    });
+}
@@ -0,0 +1,119 @@
+//! `--suggest-fixes`: creatures to fill the gaps in a deck's curve with,
+//! found through Scryfall's search.
+
+use std::collections::HashSet;
+
+use futures::{StreamExt, TryStreamExt};
+use pyre::{Card, PodGraph, PodKind};
+use scryfall::card::Color;
+use tracing::warn;
+
+use crate::Args;
+
+/// How many search results are tried in each gap, most played first.
+const CANDIDATES: usize = 60;
+
+/// How many of them are suggested.
+const SUGGESTIONS: usize = 3;
+
+/// The Scryfall search for the creatures that could fill the gap at `cmc`,
+/// within `identity`, legal in `--legal-in` and at most `--max-price`.
+fn query(cmc: u8, identity: Option<&[Color]>, args: &Args) -> String {
+    let mut query = format!("t:creature cmc={cmc} order:edhrec");
+    if let Some(identity) = identity {
+        let colors = identity
+            .iter()
+            .filter_map(|c| serde_json::to_value(c).ok())
+            .filter_map(|c| c.as_str().map(str::to_lowercase))
+            .collect::<String>();
+        let colors = if colors.is_empty() {
+            "c".into()
+        } else {
+            colors
+        };
+        query.push_str(&format!(" id<={colors}"));
+    }
+    if let Some(format) = args.legal_in {
+        if let Ok(serde_json::Value::String(format)) = serde_json::to_value(format) {
+            query.push_str(&format!(" f:{format}"));
+        }
+    }
+    if let Some(price) = args.max_price {
+        query.push_str(&format!(" usd<={price}"));
+    }
+    query
+}
+
+/// How many cards of the graph `card` would be connected to.
+fn connections<K: PodKind + Clone>(graph: &PodGraph<K>, card: &Card) -> usize {
+    let mut with = graph.clone();
+    with.add_card(card.clone());
+    with.edges()
+        .filter(|(from, to, _)| from.name == card.name || to.name == card.name)
+        .map(|(from, to, _)| {
+            if from.name == card.name {
+                &to.name
+            } else {
+                &from.name
+            }
+        })
+        .collect::<HashSet<_>>()
+        .len()
+}
+
+/// Prints, for every mana value missing between the cheapest and the most
+/// expensive creature, the creatures that would connect the most cards if
+/// added there. Candidates are searched for on Scryfall within the
+/// commanders' color identity, legal in `--legal-in`, costing at most
+/// `--max-price` and, with `--owned-only`, in the collection.
+pub(crate) async fn suggest<K: PodKind + Clone>(
+    graph: &PodGraph<K>,
+    identity: Option<&[Color]>,
+    args: &Args,
+) {
+    let cmcs = graph.cards().map(|c| c.cmc).collect::<HashSet<_>>();
+    let (Some(min), Some(max)) = (cmcs.iter().min(), cmcs.iter().max()) else {
+        println!("no creatures, nothing to connect");
+        return;
+    };
+    let gaps = (*min..=*max)
+        .filter(|cmc| !cmcs.contains(cmc))
+        .collect::<Vec<_>>();
+    if gaps.is_empty() {
+        println!("no gaps in the curve");
+        return;
+    }
+    for gap in gaps {
+        let query = query(gap, identity, args);
+        let candidates = pyre::source::search(&query)
+            .take(CANDIDATES)
+            .try_filter(|c| {
+                let keep = graph.cards().all(|d| d.name != c.name)
+                    && (!args.owned_only || args.owned.as_ref().is_none_or(|o| o.contains(c)));
+                futures::future::ready(keep)
+            })
+            .try_collect::<Vec<_>>()
+            .await;
+        let candidates = match candidates {
+            Ok(candidates) => candidates,
+            Err(e) => {
+                warn!("failed to search for creatures at mana value {gap}: {e}");
+                continue;
+            }
+        };
+        let mut connected = candidates
+            .iter()
+            .map(|c| (c, connections(graph, c)))
+            .filter(|(_, n)| *n > 0)
+            .collect::<Vec<_>>();
+        connected.sort_by_key(|(_, n)| std::cmp::Reverse(*n));
+        if connected.is_empty() {
+            println!("mana value {gap}: no creature found that connects anything");
+            continue;
+        }
+        println!("mana value {gap}, add one of these:");
+        for (card, n) in connected.into_iter().take(SUGGESTIONS) {
+            println!("    {}: connects {n} creatures", card.name);
+        }
+    }
+}
@@ -0,0 +1,51 @@
+//! Built graphs, kept in `graphs/` next to the card cache and keyed by the
+//! resolved cards and the engine, so building the graph of a list that
+//! didn't change is a single read.
+
+use std::{fmt::Debug, path::PathBuf};
+
+use pyre::{hash::fnv1a, Card, PodGraph, PodKind};
+use tracing::{debug, warn};
+
+const DIR: &str = "graphs";
+
+/// Identifies the graph `kind` builds out of `cards`. The cards are hashed
+/// whole, so a refreshed cache or an errata that changes a card's types or
+/// text changes the key instead of reusing a stale graph.
+pub(crate) fn key<K: PodKind + Debug>(kind: &K, cards: &[Card]) -> u64 {
+    let cards = serde_json::to_string(cards).expect("cards always serialize");
+    fnv1a(format!("{}{kind:?}{cards}", K::NAME).bytes())
+}
+
+fn path(key: u64) -> PathBuf {
+    PathBuf::from(DIR).join(format!("{key:016x}.json"))
+}
+
+/// The graph stored under `key`, if there is one and it still reads.
+pub(crate) async fn load<K: PodKind + Default>(key: u64, kind: K) -> Option<PodGraph<K>> {
+    let buf = tokio::fs::read(path(key)).await.ok()?;
+    match serde_json::from_slice::<PodGraph<K>>(&buf) {
+        Ok(mut graph) => {
+            debug!("graph cache hit");
+            graph.set_kind(kind);
+            Some(graph)
+        }
+        Err(e) => {
+            warn!("ignoring cached graph {}: {e}", path(key).display());
+            None
+        }
+    }
+}
+
+/// Stores the graph under `key`. Failing to is only worth a warning, the
+/// graph is just built again next time.
+pub(crate) async fn store<K: PodKind>(key: u64, graph: &PodGraph<K>) {
+    let json = serde_json::to_vec(graph).expect("graphs always serialize");
+    let stored = match tokio::fs::create_dir_all(DIR).await {
+        Ok(()) => tokio::fs::write(path(key), json).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = stored {
+        warn!("failed to write {}: {e}", path(key).display());
+    }
+}
@@ -0,0 +1,179 @@
+use std::{io, path::Path, process::Stdio};
+
+use clap::ValueEnum;
+use tokio::{io::AsyncWriteExt, process::Command};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub(crate) enum OutputFormat {
+    /// Graphviz source, no rendering
+    #[default]
+    Dot,
+    /// Rendered with graphviz' `dot -Tsvg`
+    Svg,
+    /// Rendered with graphviz' `dot -Tpng`
+    Png,
+    /// Rendered with graphviz' `dot -Tpdf`
+    Pdf,
+    /// The graph's cards and edges, for other tools to consume
+    Json,
+    /// The decklist again, grouped and sorted by pod chain
+    Decklist,
+    /// A printable html sheet of the longest chain, or of the cards that can
+    /// reach the highlighted card
+    Proxies,
+    /// A report with the graph as a Mermaid flowchart, statistics and card
+    /// lists, for primers and forum posts
+    Markdown,
+    /// A directory of numbered svg frames walking through the same chain as
+    /// `proxies`, one card at a time
+    Walkthrough,
+}
+
+impl OutputFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Dot => "dot",
+            OutputFormat::Svg => "svg",
+            OutputFormat::Png => "png",
+            OutputFormat::Pdf => "pdf",
+            OutputFormat::Json => "json",
+            OutputFormat::Decklist => "txt",
+            OutputFormat::Proxies => "html",
+            OutputFormat::Markdown => "md",
+            OutputFormat::Walkthrough => "walkthrough",
+        }
+    }
+
+    /// Whether the format is rendered from graphviz source by the graphviz
+    /// binary.
+    pub fn needs_graphviz(self) -> bool {
+        matches!(
+            self,
+            OutputFormat::Svg | OutputFormat::Png | OutputFormat::Pdf
+        )
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            OutputFormat::Dot => "text/vnd.graphviz",
+            OutputFormat::Svg => "image/svg+xml",
+            OutputFormat::Png => "image/png",
+            OutputFormat::Pdf => "application/pdf",
+            OutputFormat::Json => "application/json",
+            OutputFormat::Decklist => "text/plain",
+            OutputFormat::Proxies => "text/html",
+            OutputFormat::Markdown => "text/markdown",
+            OutputFormat::Walkthrough => "inode/directory",
+        }
+    }
+}
+
+/// The graphviz layout engine graphs are rendered with.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub(crate) enum Layout {
+    /// Layered, keeping the mana value rows
+    #[default]
+    Dot,
+    /// Spring model, for small graphs
+    Neato,
+    /// Force directed, keeping the mana value clusters
+    Fdp,
+    /// Force directed and multiscale, for big graphs like cubes. Ignores the
+    /// mana value clusters
+    Sfdp,
+}
+
+impl Layout {
+    /// The `dot` arguments selecting the engine, and the graph attributes
+    /// that keep its layouts readable.
+    fn args(self) -> &'static [&'static str] {
+        match self {
+            Layout::Dot => &["-Kdot"],
+            Layout::Neato => &["-Kneato", "-Goverlap=false", "-Gsplines=true"],
+            Layout::Fdp => &["-Kfdp", "-Goverlap=prism", "-Gsplines=true"],
+            Layout::Sfdp => &[
+                "-Ksfdp",
+                "-Goverlap=prism",
+                "-Gsplines=true",
+                "-Gbeautify=true",
+            ],
+        }
+    }
+}
+
+/// Renders the dot file at `source` into `output`, using the graphviz binary.
+#[tracing::instrument(level = "debug", skip_all, fields(format = ?format, layout = ?layout))]
+pub(crate) async fn render(
+    source: &Path,
+    format: OutputFormat,
+    layout: Layout,
+    output: &Path,
+) -> io::Result<()> {
+    if !format.needs_graphviz() {
+        return Ok(());
+    }
+    let status = Command::new("dot")
+        .args(layout.args())
+        .arg(format!("-T{}", format.extension()))
+        .arg(source)
+        .arg("-o")
+        .arg(output)
+        .status()
+        .await?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!("graphviz exited with {status}")))
+    }
+}
+
+/// Renders graphviz source in memory, piping it through the graphviz binary.
+/// Formats that don't [need graphviz](OutputFormat::needs_graphviz) are
+/// returned as is.
+#[tracing::instrument(level = "debug", skip_all, fields(format = ?format, layout = ?layout))]
+pub(crate) async fn render_bytes(
+    dot: String,
+    format: OutputFormat,
+    layout: Layout,
+) -> io::Result<Vec<u8>> {
+    if !format.needs_graphviz() {
+        return Ok(dot.into_bytes());
+    }
+    let mut child = Command::new("dot")
+        .args(layout.args())
+        .arg(format!("-T{}", format.extension()))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()?;
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let write = async move {
+        stdin.write_all(dot.as_bytes()).await?;
+        stdin.shutdown().await
+    };
+    let (written, output) = tokio::join!(write, child.wait_with_output());
+    let output = output?;
+    written?;
+    if output.status.success() {
+        Ok(output.stdout)
+    } else {
+        Err(io::Error::other(format!(
+            "graphviz exited with {}",
+            output.status
+        )))
+    }
+}
+
+/// Opens `path` with the platform's default viewer, without waiting for it
+/// to close.
+pub(crate) fn open(path: &Path) -> io::Result<()> {
+    let mut command = if cfg!(target_os = "macos") {
+        Command::new("open")
+    } else if cfg!(windows) {
+        let mut c = Command::new("cmd");
+        c.args(["/C", "start", ""]);
+        c
+    } else {
+        Command::new("xdg-open")
+    };
+    command.arg(path).spawn().map(drop)
+}
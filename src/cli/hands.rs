@@ -0,0 +1,146 @@
+//! `simulate-hands`: how often a shuffled deck gives an engine and a creature
+//! that climbs to each payoff, rather than whether one could.
+
+use std::{collections::HashSet, path::Path};
+
+use pyre::{
+    decklist,
+    error::{self, Error},
+    InputFormat, PodGraph, PodKind,
+};
+use tokio::fs::File;
+use tracing::info;
+
+use crate::{
+    cli::{
+        cube::{seed_or_now, Rng},
+        engine,
+        progress::Progress,
+        report,
+    },
+    collect_cards, report_skipped, Pod, Source,
+};
+
+/// How the hands are dealt.
+pub(crate) struct Deal {
+    /// How many hands to shuffle.
+    pub(crate) hands: usize,
+    /// How many cards an opening hand has.
+    pub(crate) hand_size: usize,
+    /// How many cards are drawn after the opening hand.
+    pub(crate) draws: usize,
+}
+
+/// Resolves the decklist, then deals it as `deal` says, and prints how often those
+/// cards had one of the [engine cards](engine::cards_for) of `pod` and a
+/// creature that can climb to each of the [payoffs](report::payoffs). Every
+/// copy of a card is shuffled in, and commanders are left out of the
+/// library, a commander engine is always there.
+pub(crate) async fn simulate<K: PodKind + Default>(
+    pod: Pod,
+    path: &Path,
+    deal: Deal,
+    seed: Option<u64>,
+    source: &Source,
+    progress: &Progress,
+) -> error::Result<()> {
+    let file = File::open(path).await.map_err(Error::Input)?;
+    let cards = decklist::parse(
+        file,
+        source.clone(),
+        InputFormat::Auto,
+        decklist::DEFAULT_CONCURRENCY,
+        progress.clone(),
+        false,
+        Some,
+    )
+    .await;
+    let cards = decklist::in_file(cards, &path.to_string_lossy());
+    let (cards, failed) = collect_cards(Box::pin(cards), progress).await?;
+    report_skipped(failed);
+    let Deal {
+        hands,
+        hand_size,
+        draws,
+    } = deal;
+    let mut library = cards
+        .iter()
+        .filter(|c| !c.commander)
+        .flat_map(|c| std::iter::repeat_n(c.name.as_str(), c.count as usize))
+        .collect::<Vec<_>>();
+    let seen = hand_size + draws;
+    if seen == 0 || seen > library.len() {
+        return Err(Error::Input(std::io::Error::other(format!(
+            "can't see {seen} cards of a library of {}",
+            library.len()
+        ))));
+    }
+    let graph = PodGraph::<K>::builder()
+        .add_cards(cards.iter().cloned().filter_map(decklist::into_creature))
+        .build();
+    let engines = engine::cards_for(pod, None);
+    let found = cards
+        .iter()
+        .filter(|c| engine::is_engine(&engines, &c.name))
+        .map(|c| c.name.clone())
+        .collect::<Vec<_>>();
+    engine::warn_if_missing(pod, &engines, &found);
+    let engine_in_command_zone = engines.is_empty()
+        || cards
+            .iter()
+            .any(|c| c.commander && engine::is_engine(&engines, &c.name));
+    let tops = report::payoffs(&graph)
+        .into_iter()
+        .map(|top| {
+            let climbers = graph
+                .cards_that_can_reach(&top.name)
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|c| c.name != top.name)
+                .map(|c| c.name.as_str())
+                .collect::<HashSet<_>>();
+            (top.name.as_str(), climbers)
+        })
+        .collect::<Vec<_>>();
+    if tops.is_empty() {
+        println!("no chains to climb");
+        return Ok(());
+    }
+    let seed = seed_or_now(seed);
+    info!(seed, "shuffling {hands} hands");
+    let mut rng = Rng::new(seed);
+    let mut live = vec![0usize; tops.len()];
+    let mut any = 0;
+    for _ in 0..hands {
+        // partial Fisher-Yates: the first `seen` cards end up a uniform draw
+        for i in 0..seen {
+            let j = i + rng.below(library.len() - i);
+            library.swap(i, j);
+        }
+        let drawn = &library[..seen];
+        if !engine_in_command_zone && !drawn.iter().any(|c| engine::is_engine(&engines, c)) {
+            continue;
+        }
+        let mut any_live = false;
+        for ((_, climbers), live) in tops.iter().zip(&mut live) {
+            if drawn.iter().any(|c| climbers.contains(c)) {
+                *live += 1;
+                any_live = true;
+            }
+        }
+        any += usize::from(any_live);
+    }
+    let rate = |n: usize| n as f64 / hands.max(1) as f64 * 100.0;
+    println!("{hands} hands of {hand_size} cards and {draws} draws");
+    println!("a chain to any payoff: {:.1}%", rate(any));
+    let mut by_rate = tops
+        .iter()
+        .map(|(name, _)| *name)
+        .zip(live)
+        .collect::<Vec<_>>();
+    by_rate.sort_by_key(|(_, live)| std::cmp::Reverse(*live));
+    for (name, live) in by_rate {
+        println!("    {:>5.1}% {name}", rate(live));
+    }
+    Ok(())
+}
@@ -0,0 +1,144 @@
+//! `--history-dir`: every run appends what analysing the graph found to the
+//! deck's history, so `trend` can show how its revisions compare.
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use pyre::{
+    error::{self, Error},
+    hash::fnv1a,
+    PodGraph, PodKind,
+};
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+use tracing::warn;
+
+use crate::Args;
+
+/// One line of a deck's history.
+#[derive(Serialize, Deserialize)]
+struct Snapshot {
+    date: String,
+    /// Tells the revisions of the deck apart: a hash of the names of the
+    /// cards in the graph.
+    revision: String,
+    creatures: usize,
+    edges: usize,
+    orphans: usize,
+    /// `None` if the graph has a cycle.
+    longest_chain: Option<usize>,
+}
+
+/// The history of `deck`, named after its file.
+fn path(dir: &Path, deck: &Path) -> PathBuf {
+    let mut name = deck.file_stem().unwrap_or_default().to_owned();
+    name.push(".jsonl");
+    dir.join(name)
+}
+
+async fn append(path: &Path, snapshot: &Snapshot) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        tokio::fs::create_dir_all(dir).await?;
+    }
+    let mut line = serde_json::to_vec(snapshot)?;
+    line.push(b'\n');
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await?;
+    file.write_all(&line).await?;
+    file.flush().await
+}
+
+/// Appends a snapshot of the graph to the history of `--history-dir`, if
+/// there is one. Failing to is only worth a warning, the output was written
+/// all the same.
+pub(crate) async fn record<K: PodKind>(graph: &PodGraph<K>, args: &Args) {
+    let (Some(dir), Some(deck)) = (&args.history_dir, &args.file) else {
+        return;
+    };
+    let mut names = graph.cards().map(|c| c.name.as_str()).collect::<Vec<_>>();
+    names.sort_unstable();
+    let snapshot = Snapshot {
+        date: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        revision: format!("{:016x}", fnv1a(names.join("\n").bytes())),
+        creatures: names.len(),
+        edges: graph.edges().count(),
+        orphans: graph.orphans().len(),
+        longest_chain: graph.longest_chain().map(|c| c.len()),
+    };
+    let path = path(dir, deck);
+    if let Err(e) = append(&path, &snapshot).await {
+        warn!("failed to write {}: {e}", path.display());
+    }
+}
+
+/// `(+2)`, `(-1)` or nothing when `now` and `before` are the same or either
+/// is missing.
+fn delta(now: Option<usize>, before: Option<usize>) -> String {
+    match (now, before) {
+        (Some(now), Some(before)) if now != before => {
+            format!("({:+})", now as i64 - before as i64)
+        }
+        _ => String::new(),
+    }
+}
+
+/// Prints how the orphan count and the longest chain of `deck` changed
+/// across the revisions in its history. Runs that didn't change the cards
+/// are left out, so only the first snapshot of each revision is shown.
+/// Lines that can't be read are skipped.
+pub(crate) async fn trend(dir: &Path, deck: &Path) -> error::Result<()> {
+    let path = path(dir, deck);
+    let text = tokio::fs::read_to_string(&path).await.map_err(|e| {
+        Error::Input(io::Error::new(
+            e.kind(),
+            format!(
+                "no history for {} in {}: {e}",
+                deck.display(),
+                dir.display()
+            ),
+        ))
+    })?;
+    let mut revisions = Vec::<Snapshot>::new();
+    for snapshot in text
+        .lines()
+        .filter_map(|l| serde_json::from_str::<Snapshot>(l).ok())
+    {
+        if revisions
+            .last()
+            .is_none_or(|last| last.revision != snapshot.revision)
+        {
+            revisions.push(snapshot);
+        }
+    }
+    let date_width = revisions.iter().map(|s| s.date.len()).max().unwrap_or(0);
+    println!("{:date_width$}  creatures  orphans  longest chain", "date");
+    let mut previous: Option<&Snapshot> = None;
+    for snapshot in &revisions {
+        let orphans = format!(
+            "{} {}",
+            snapshot.orphans,
+            delta(Some(snapshot.orphans), previous.map(|p| p.orphans))
+        );
+        let chain = match snapshot.longest_chain {
+            Some(len) => format!(
+                "{len} {}",
+                delta(Some(len), previous.and_then(|p| p.longest_chain))
+            ),
+            None => "cycle".into(),
+        };
+        println!(
+            "{:date_width$}  {:>9}  {:7}  {}",
+            snapshot.date,
+            snapshot.creatures,
+            orphans.trim_end(),
+            chain.trim_end()
+        );
+        previous = Some(snapshot);
+    }
+    Ok(())
+}
@@ -0,0 +1,44 @@
+use clap::ValueEnum;
+use tracing::Level;
+
+use crate::cli::progress::Progress;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Verbosity {
+    Quiet,
+    Normal,
+    Verbose,
+    Trace,
+}
+
+impl Verbosity {
+    fn level(self) -> Level {
+        match self {
+            Verbosity::Quiet => Level::ERROR,
+            Verbosity::Normal => Level::INFO,
+            Verbosity::Verbose => Level::DEBUG,
+            Verbosity::Trace => Level::TRACE,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub(crate) enum LogFormat {
+    /// Human readable lines
+    #[default]
+    Text,
+    /// One json object per line, for wrapper scripts
+    Json,
+}
+
+/// Installs the global tracing subscriber. Log lines are routed through the
+/// progress bar so they don't tear it.
+pub(crate) fn init(verbosity: Verbosity, format: LogFormat, progress: &Progress) {
+    let builder = tracing_subscriber::fmt()
+        .with_max_level(verbosity.level())
+        .with_writer(progress.clone());
+    match format {
+        LogFormat::Text => builder.without_time().with_target(false).init(),
+        LogFormat::Json => builder.json().with_current_span(true).init(),
+    }
+}
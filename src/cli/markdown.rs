@@ -0,0 +1,117 @@
+//! Markdown reports, with the graph as a Mermaid flowchart, for primers and
+//! forum posts.
+
+use std::{collections::BTreeMap, fmt::Write};
+
+use pyre::{EdgeFilter, PodEdge, PodGraph, PodKind, RenderOptions};
+
+use crate::cli::report;
+
+/// Mermaid labels are quoted, and quotes in them are written as entities.
+fn escape(s: &str) -> String {
+    s.replace('"', "#quot;")
+}
+
+/// The graph as a Mermaid flowchart, with a subgraph per mana value. Edges
+/// `edge_filter` hides are left out.
+fn mermaid<K: PodKind>(graph: &PodGraph<K>, edge_filter: &EdgeFilter) -> String {
+    let mut out = String::from("```mermaid\nflowchart LR\n");
+    let ids = graph
+        .cards()
+        .enumerate()
+        .map(|(i, c)| (c.name.as_str(), i))
+        .collect::<BTreeMap<_, _>>();
+    let by_cmc = graph
+        .cards()
+        .fold(BTreeMap::<_, Vec<_>>::new(), |mut acc, c| {
+            acc.entry(c.cmc).or_default().push(c);
+            acc
+        });
+    for (cmc, cards) in by_cmc {
+        let _ = writeln!(out, "    subgraph mv{cmc} [\"{cmc}\"]");
+        for card in cards {
+            let _ = writeln!(
+                out,
+                "        n{}[\"{}\"]",
+                ids[card.name.as_str()],
+                escape(&card.name)
+            );
+        }
+        out.push_str("    end\n");
+    }
+    for (from, to, edge) in graph.edges() {
+        let label = edge.label();
+        if !edge_filter.shows(&label) {
+            continue;
+        }
+        let arrow = match label.as_str() {
+            "" => "-->".to_owned(),
+            label => format!("-->|\"{}\"|", escape(label)),
+        };
+        let _ = writeln!(
+            out,
+            "    n{} {arrow} n{}",
+            ids[from.name.as_str()],
+            ids[to.name.as_str()]
+        );
+    }
+    out.push_str("```\n");
+    out
+}
+
+/// A report with the graph, a table of its totals, its curve, its longest
+/// chains, how redundant the chains to its payoffs are, suggestions for
+/// improving it and its cards by mana value.
+pub(crate) fn report<K: PodKind + Clone>(graph: &PodGraph<K>, options: &RenderOptions) -> String {
+    let name = graph.metadata().and_then(|m| m.name.as_deref());
+    let mut out = format!("# {}\n\n", name.unwrap_or("Pod graph"));
+    out.push_str(&mermaid(graph, &options.edge_filter));
+    let chain = graph
+        .longest_chain()
+        .map_or("cycle".into(), |c| c.len().to_string());
+    let _ = write!(
+        out,
+        "\n## Statistics\n\n\
+         | creatures | edges | orphans | longest chain |\n\
+         |---|---|---|---|\n\
+         | {} | {} | {} | {chain} |\n",
+        graph.cards().count(),
+        graph.edges().count(),
+        graph.orphans().len(),
+    );
+    let by_cmc = graph
+        .cards()
+        .fold(BTreeMap::<_, Vec<_>>::new(), |mut acc, c| {
+            acc.entry(c.cmc).or_default().push(c.name.as_str());
+            acc
+        });
+    out.push_str("\n| mana value | creatures | cards |\n|---|---|---|\n");
+    for (cmc, names) in &by_cmc {
+        let _ = writeln!(
+            out,
+            "| {cmc} | {} | {} |",
+            names.len(),
+            names.join(", ").replace('|', "\\|")
+        );
+    }
+    out.push_str("\n## Longest chains\n\n");
+    for chain in report::chains(graph) {
+        let _ = writeln!(out, "- {chain}");
+    }
+    out.push_str("\n## Redundancy\n\n");
+    for line in report::redundancy(graph) {
+        let _ = writeln!(out, "- {line}");
+    }
+    out.push_str("\n## Suggestions\n\n");
+    for suggestion in report::suggestions(graph) {
+        let _ = writeln!(out, "- {suggestion}");
+    }
+    out.push_str("\n## Cards\n");
+    for (cmc, names) in by_cmc {
+        let _ = writeln!(out, "\n### {cmc}\n");
+        for name in names {
+            let _ = writeln!(out, "- {name}");
+        }
+    }
+    out
+}
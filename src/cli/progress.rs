@@ -0,0 +1,73 @@
+use std::io::{self, Write};
+
+use indicatif::{ProgressBar, ProgressStyle};
+use pyre::ResolveProgress;
+use tracing_subscriber::fmt::MakeWriter;
+
+use crate::cli::events;
+
+#[derive(Debug, Clone)]
+pub(crate) struct Progress {
+    bar: ProgressBar,
+    /// Whether to [emit](events::emit) an event for every resolved line.
+    events: bool,
+}
+
+impl Progress {
+    pub fn new(hidden: bool, events: bool) -> Self {
+        let bar = match hidden {
+            true => ProgressBar::hidden(),
+            false => ProgressBar::new(0).with_style(
+                ProgressStyle::with_template("{spinner} [{bar:30}] {pos}/{len} {wide_msg}")
+                    .unwrap()
+                    .progress_chars("=> "),
+            ),
+        };
+        Self { bar, events }
+    }
+
+    /// Starts counting from zero again, for when the decklist is re-read.
+    pub fn reset(&self) {
+        self.bar.reset();
+        self.bar.set_length(0);
+    }
+
+    pub fn finish(&self) {
+        self.bar.finish_and_clear();
+    }
+}
+
+impl ResolveProgress for Progress {
+    fn queued(&self) {
+        self.bar.inc_length(1);
+    }
+
+    fn fetched(&self, name: &str) {
+        self.bar.set_message(name.to_owned());
+        self.bar.inc(1);
+        if self.events {
+            events::emit(&events::Event::Resolved { name });
+        }
+    }
+}
+
+/// Writes log lines to stderr without tearing the progress bar.
+pub(crate) struct ProgressWriter(ProgressBar);
+
+impl Write for ProgressWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.suspend(|| io::stderr().write(buf))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stderr().flush()
+    }
+}
+
+impl<'a> MakeWriter<'a> for Progress {
+    type Writer = ProgressWriter;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        ProgressWriter(self.bar.clone())
+    }
+}
@@ -0,0 +1,67 @@
+//! Printable proxy sheets of the cards in a chain.
+
+use std::fmt::Write;
+
+use pyre::{Card, PodGraph, PodKind};
+use tracing::warn;
+
+/// The cards a sheet is printed, or a walkthrough drawn, for: the
+/// highlighted chain or the ones that can reach the highlighted card, sorted
+/// by mana value, or the longest chain when nothing is highlighted.
+pub(crate) fn chain<K: PodKind>(graph: &PodGraph<K>) -> Vec<&Card> {
+    if let Some(names) = graph.highlight_chain() {
+        let mut cards = graph
+            .cards()
+            .filter(|c| names.contains(&c.name))
+            .collect::<Vec<_>>();
+        cards.sort_by_key(|c| c.cmc);
+        return cards;
+    }
+    if graph.highlight().is_some() {
+        let target = graph.highlight_matches().into_iter().next();
+        let mut cards = target
+            .and_then(|t| graph.cards_that_can_reach(&t.name))
+            .unwrap_or_default();
+        cards.sort_by_key(|c| c.cmc);
+        return cards;
+    }
+    graph.longest_chain().unwrap_or_else(|| {
+        warn!("the graph has a cycle, printing every card");
+        graph.cards().collect()
+    })
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// An html page with the cards at their real size, nine to a printed page:
+/// three 63mm wide cards and the gaps between them fill the 190mm an A4 page
+/// has inside its margins.
+/// Images are loaded from Scryfall by the browser, which caches them.
+pub(crate) fn sheet(cards: &[&Card]) -> String {
+    let mut out = String::from(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Proxies</title>\n\
+         <style>\n\
+         @page { size: A4; margin: 10mm; }\n\
+         body { margin: 0; display: flex; flex-wrap: wrap; gap: 0.5mm; width: 190mm; }\n\
+         img { width: 63mm; height: 88mm; break-inside: avoid; }\n\
+         </style>\n</head>\n<body>\n",
+    );
+    for card in cards {
+        let name = url::form_urlencoded::byte_serialize(card.name.as_bytes()).collect::<String>();
+        let _ = writeln!(
+            out,
+            "<img src=\"https://api.scryfall.com/cards/named?exact={name}&amp;format=image\" \
+             alt=\"{}\" title=\"{} ({})\">",
+            escape(&card.name),
+            escape(&card.name),
+            card.cmc,
+        );
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
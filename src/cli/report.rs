@@ -0,0 +1,233 @@
+//! `report`: a pdf to share a deck's graph and what it says about the deck.
+//!
+//! Every page is a graph of its own, the text pages being a single plaintext
+//! node, so that graphviz renders them all into one multi-page pdf.
+
+use std::{collections::BTreeMap, fmt::Write, path::Path};
+
+use pyre::{
+    decklist,
+    error::{self, Error},
+    Card, InputFormat, Metadata, PodGraph, PodKind, RenderOptions,
+};
+use tokio::fs::File;
+use tracing::info;
+
+use crate::{
+    cli::{
+        graphviz::{self, Layout, OutputFormat},
+        progress::Progress,
+    },
+    collect_cards, report_skipped, Source,
+};
+
+/// A page of monospaced text, as graphviz source.
+fn text_page(title: &str, lines: &[String]) -> String {
+    let escape = |s: &str| s.replace('\\', "\\\\").replace('"', "\\\"");
+    let mut label = format!("{}\\l\\l", escape(title));
+    for line in lines {
+        let _ = write!(label, "{}\\l", escape(line));
+    }
+    format!(
+        "digraph {{\n    node [shape=plaintext fontname=\"Courier\"]\n    page [label = \"{label}\"]\n}}\n"
+    )
+}
+
+fn names(cards: &[&Card]) -> String {
+    cards
+        .iter()
+        .map(|c| c.name.as_str())
+        .collect::<Vec<_>>()
+        .join(" -> ")
+}
+
+/// How many creatures there are at each mana value, as a histogram.
+fn curve<K: PodKind>(graph: &PodGraph<K>) -> Vec<String> {
+    let by_cmc = graph.cards().fold(BTreeMap::new(), |mut acc, c| {
+        *acc.entry(c.cmc).or_insert(0usize) += 1;
+        acc
+    });
+    let (Some(min), Some(max)) = (by_cmc.keys().next(), by_cmc.keys().last()) else {
+        return vec!["no creatures".into()];
+    };
+    (*min..=*max)
+        .map(|cmc| {
+            let count = by_cmc.get(&cmc).copied().unwrap_or(0);
+            format!("{cmc:>3}: {count:>3} {}", "#".repeat(count))
+        })
+        .collect()
+}
+
+fn statistics<K: PodKind>(graph: &PodGraph<K>) -> Vec<String> {
+    let orphans = graph.orphans();
+    let mut lines = vec![
+        format!("creatures: {}", graph.cards().count()),
+        format!("edges: {}", graph.edges().count()),
+        format!("orphans: {}", orphans.len()),
+        format!(
+            "chain tops one game can assemble: {}",
+            graph.payoffs_in_one_game().len()
+        ),
+        String::new(),
+        "creatures per mana value:".into(),
+    ];
+    lines.extend(curve(graph));
+    lines.push(String::new());
+    lines.push("orphans:".into());
+    lines.extend(orphans.iter().map(|c| format!("  {} ({})", c.name, c.cmc)));
+    lines
+}
+
+/// The longest chain of each group of connected cards, longest first.
+pub(crate) fn chains<K: PodKind + Clone>(graph: &PodGraph<K>) -> Vec<String> {
+    let mut chains = Vec::new();
+    for component in graph.components().into_iter().filter(|c| c.len() > 1) {
+        let mut only = graph.clone();
+        for card in graph.cards().filter(|c| !component.contains(c)) {
+            only.remove_card(&card.name);
+        }
+        match only.longest_chain() {
+            Some(chain) => chains.push((chain.len(), names(&chain))),
+            None => chains.push((0, format!("a cycle through {}", names(&component)))),
+        }
+    }
+    chains.sort_by_key(|(len, _)| std::cmp::Reverse(*len));
+    if chains.is_empty() {
+        return vec!["no two creatures are connected".into()];
+    }
+    chains
+        .into_iter()
+        .map(|(len, chain)| match len {
+            0 => chain,
+            len => format!("{len}: {chain}"),
+        })
+        .collect()
+}
+
+/// The cards the deck climbs towards: the ones tagged `#payoff` or, if
+/// there are none, the [tops of the chains](PodGraph::chain_tops).
+pub(crate) fn payoffs<K: PodKind>(graph: &PodGraph<K>) -> Vec<&Card> {
+    let tagged = graph
+        .cards()
+        .filter(|c| c.tags.iter().any(|t| t.eq_ignore_ascii_case("payoff")))
+        .collect::<Vec<_>>();
+    match tagged.is_empty() {
+        true => graph.chain_tops(),
+        false => tagged,
+    }
+}
+
+/// How many chains climb to each of the [payoffs] without sharing an edge,
+/// and from which mana values.
+pub(crate) fn redundancy<K: PodKind>(graph: &PodGraph<K>) -> Vec<String> {
+    let payoffs = payoffs(graph);
+    if payoffs.is_empty() {
+        return vec!["no payoffs, nothing climbs anywhere".into()];
+    }
+    payoffs
+        .into_iter()
+        .filter_map(|p| Some((p, graph.redundancy(&p.name)?)))
+        .map(|(payoff, r)| {
+            let cmcs = r
+                .starting_cmcs
+                .iter()
+                .map(u8::to_string)
+                .collect::<Vec<_>>()
+                .join(", ");
+            let values = match r.starting_cmcs.len() {
+                1 => "value",
+                _ => "values",
+            };
+            match r.chains {
+                0 => format!("{}: nothing climbs to it", payoff.name),
+                chains => format!(
+                    "{}: {chains} chains, starting at mana {values} {cmcs}",
+                    payoff.name
+                ),
+            }
+        })
+        .collect()
+}
+
+/// Gaps in the curve, which break every chain across them, and orphans,
+/// which want a neighbour.
+pub(crate) fn suggestions<K: PodKind>(graph: &PodGraph<K>) -> Vec<String> {
+    let cmcs = graph.cards().map(|c| c.cmc).collect::<Vec<_>>();
+    let mut lines = Vec::new();
+    if let (Some(min), Some(max)) = (cmcs.iter().min(), cmcs.iter().max()) {
+        lines.extend(
+            (*min..=*max)
+                .filter(|cmc| !cmcs.contains(cmc))
+                .map(|cmc| format!("add a creature at {cmc}, no chain gets past it")),
+        );
+    }
+    lines.extend(graph.orphans().into_iter().map(|c| {
+        format!(
+            "{} ({}) connects to nothing, add a creature next to it or cut it",
+            c.name, c.cmc
+        )
+    }));
+    if lines.is_empty() {
+        lines.push("nothing, the deck climbs fine".into());
+    }
+    lines
+}
+
+/// Resolves the decklist at `path`, builds its graph and writes a pdf to
+/// `output` with the graph, its statistics, its longest chains, how
+/// redundant the chains to its payoffs are and suggestions for improving it.
+pub(crate) async fn report<K: PodKind + Clone + Default>(
+    path: &Path,
+    output: &Path,
+    layout: Layout,
+    source: &Source,
+    progress: &Progress,
+) -> error::Result<()> {
+    let file = File::open(path).await.map_err(Error::Input)?;
+    let cards = decklist::parse(
+        file,
+        source.clone(),
+        InputFormat::Auto,
+        decklist::DEFAULT_CONCURRENCY,
+        progress.clone(),
+        false,
+        decklist::into_creature,
+    )
+    .await;
+    let cards = decklist::in_file(cards, &path.to_string_lossy());
+    let (cards, failed) = collect_cards(Box::pin(cards), progress).await?;
+    report_skipped(failed);
+    let name = path.file_stem().map(|s| s.to_string_lossy().into_owned());
+    let graph = PodGraph::<K>::builder()
+        .add_cards(cards)
+        .metadata(Metadata {
+            name: name.clone(),
+            date: None,
+        })
+        .build();
+    let title = name.as_deref().unwrap_or("deck");
+    let mut pages = graph.to_dot(&RenderOptions::default()).await;
+    pages.push('\n');
+    pages.push_str(&text_page(
+        &format!("{title}: statistics"),
+        &statistics(&graph),
+    ));
+    pages.push_str(&text_page(
+        &format!("{title}: longest chains"),
+        &chains(&graph),
+    ));
+    pages.push_str(&text_page(
+        &format!("{title}: redundancy"),
+        &redundancy(&graph),
+    ));
+    pages.push_str(&text_page(
+        &format!("{title}: suggestions"),
+        &suggestions(&graph),
+    ));
+    let pdf = graphviz::render_bytes(pages, OutputFormat::Pdf, layout)
+        .await
+        .map_err(Error::Output)?;
+    tokio::fs::write(output, pdf).await.map_err(Error::Output)?;
+    info!("wrote {}", output.display());
+    Ok(())
+}
@@ -0,0 +1,133 @@
+//! `--require`: conditions every creature must meet to be in the graph, to
+//! approximate engines that only fetch some creatures, like Recruiter of the
+//! Guard's `toughness<=2`.
+
+use std::{cmp::Ordering, str::FromStr};
+
+use pyre::Card;
+use scryfall::card::Color;
+
+#[derive(Debug, Clone, Copy)]
+enum Stat {
+    Power,
+    Toughness,
+    ManaValue,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Op {
+    Lt,
+    Le,
+    Eq,
+    Ne,
+    Ge,
+    Gt,
+}
+
+impl Op {
+    fn holds(self, ordering: Ordering) -> bool {
+        match self {
+            Op::Lt => ordering.is_lt(),
+            Op::Le => ordering.is_le(),
+            Op::Eq => ordering.is_eq(),
+            Op::Ne => ordering.is_ne(),
+            Op::Ge => ordering.is_ge(),
+            Op::Gt => ordering.is_gt(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Condition {
+    /// The word is in the card's type line, like `legendary`.
+    Type(String),
+    /// The color is in the card's color identity, like `green`. Close
+    /// enough to the card's color for creatures.
+    Color(Color),
+    /// One of the card's stats compares to a number.
+    Compare { stat: Stat, op: Op, value: i32 },
+}
+
+/// A condition, possibly negated with a leading `!`.
+#[derive(Debug, Clone)]
+pub(crate) struct Requirement {
+    negated: bool,
+    condition: Condition,
+}
+
+impl FromStr for Requirement {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (negated, s) = match s.strip_prefix('!') {
+            Some(rest) => (true, rest.trim_start()),
+            None => (false, s),
+        };
+        let Some(at) = s.find(['<', '>', '=', '!']) else {
+            if s.is_empty() || s.contains(char::is_whitespace) {
+                return Err(format!("expected a type or a comparison, got '{s}'"));
+            }
+            let condition = match s.to_lowercase().as_str() {
+                "white" => Condition::Color(Color::White),
+                "blue" => Condition::Color(Color::Blue),
+                "black" => Condition::Color(Color::Black),
+                "red" => Condition::Color(Color::Red),
+                "green" => Condition::Color(Color::Green),
+                _ => Condition::Type(s.to_owned()),
+            };
+            return Ok(Self { negated, condition });
+        };
+        let (stat, rest) = s.split_at(at);
+        let stat = match stat.trim().to_lowercase().as_str() {
+            "power" | "pow" => Stat::Power,
+            "toughness" | "tou" => Stat::Toughness,
+            "mv" | "cmc" => Stat::ManaValue,
+            other => {
+                return Err(format!(
+                    "unknown stat '{other}', expected power, toughness or mv"
+                ))
+            }
+        };
+        let (op, value) = [
+            ("<=", Op::Le),
+            (">=", Op::Ge),
+            ("!=", Op::Ne),
+            ("==", Op::Eq),
+            ("<", Op::Lt),
+            (">", Op::Gt),
+            ("=", Op::Eq),
+        ]
+        .into_iter()
+        .find_map(|(token, op)| rest.strip_prefix(token).map(|value| (op, value)))
+        .ok_or_else(|| format!("unknown comparison in '{s}'"))?;
+        let value = value
+            .trim()
+            .parse()
+            .map_err(|e| format!("'{}' is not a number: {e}", value.trim()))?;
+        Ok(Self {
+            negated,
+            condition: Condition::Compare { stat, op, value },
+        })
+    }
+}
+
+impl Requirement {
+    /// Whether the card meets the requirement. Cards without the stat being
+    /// compared, or with one that isn't a number like `*`, never meet it.
+    pub(crate) fn matches(&self, card: &Card) -> bool {
+        let met = match &self.condition {
+            Condition::Type(ty) => card.types.iter().any(|t| t.eq_ignore_ascii_case(ty)),
+            Condition::Color(color) => card.color_identity.contains(color),
+            Condition::Compare { stat, op, value } => {
+                let stat = match stat {
+                    Stat::Power => card.power.as_deref().and_then(|p| p.parse().ok()),
+                    Stat::Toughness => card.toughness.as_deref().and_then(|t| t.parse().ok()),
+                    Stat::ManaValue => Some(i32::from(card.cmc)),
+                };
+                stat.is_some_and(|stat: i32| op.holds(stat.cmp(value)))
+            }
+        };
+        met != self.negated
+    }
+}
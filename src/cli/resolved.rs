@@ -0,0 +1,21 @@
+//! Snapshots of resolved decklists, so the same cards can be rendered again
+//! without looking them up.
+
+use std::path::Path;
+
+use pyre::{
+    error::{self, Error},
+    Card,
+};
+
+/// Writes the cards to `path`, as a json array.
+pub(crate) async fn save(path: &Path, cards: &[Card]) -> error::Result<()> {
+    let json = serde_json::to_vec(cards).expect("cards always serialize");
+    tokio::fs::write(path, json).await.map_err(Error::Output)
+}
+
+/// Reads back cards written by [save].
+pub(crate) async fn load(path: &Path) -> error::Result<Vec<Card>> {
+    let json = tokio::fs::read(path).await.map_err(Error::Input)?;
+    serde_json::from_slice(&json).map_err(|e| Error::Input(e.into()))
+}
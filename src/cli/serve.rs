@@ -0,0 +1,181 @@
+//! `serve`: builds graphs over HTTP.
+//!
+//! `POST /graph` takes the decklist as its body, `GET /graph?url=<moxfield
+//! url>` imports it from Moxfield. Both take the optional `pod`, `format`,
+//! `layout` and `highlight` query parameters, named and valued like the
+//! command line flags.
+
+use std::{collections::HashMap, convert::Infallible, io, net::SocketAddr};
+
+use clap::ValueEnum;
+use futures::TryStreamExt;
+use hyper::{
+    body::HttpBody,
+    header::CONTENT_TYPE,
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server, StatusCode,
+};
+use pyre::{
+    decklist,
+    error::{self, Error},
+    moxfield, FailedLine, InputFormat, PodGraph, PodKind, RenderOptions,
+};
+use tracing::{info, warn};
+
+use crate::{
+    cli::{graphviz, markdown, proxies},
+    graphviz::{Layout, OutputFormat},
+    with_kind, Pod, Source,
+};
+
+/// Decklists longer than this are rejected.
+const MAX_BODY: usize = 1 << 20;
+
+type HttpResult = Result<Response<Body>, (StatusCode, String)>;
+
+/// Serves graphs on `addr` until the process is killed.
+pub(crate) async fn serve(addr: SocketAddr, source: Source) -> error::Result<()> {
+    let make_service = make_service_fn(move |_| {
+        let source = source.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req| {
+                let source = source.clone();
+                async move {
+                    let method = req.method().clone();
+                    let path = req.uri().path().to_owned();
+                    let response = handle(req, &source).await.unwrap_or_else(|(status, e)| {
+                        warn!(%method, path, %status, "{e}");
+                        Response::builder()
+                            .status(status)
+                            .body(Body::from(e + "\n"))
+                            .expect("the response is valid")
+                    });
+                    Ok::<_, Infallible>(response)
+                }
+            }))
+        }
+    });
+    let server = Server::try_bind(&addr)
+        .map_err(|e| Error::Input(io::Error::other(e)))?
+        .serve(make_service);
+    info!("listening on http://{addr}");
+    server.await.map_err(|e| Error::Output(io::Error::other(e)))
+}
+
+async fn handle(req: Request<Body>, source: &Source) -> HttpResult {
+    if req.uri().path() != "/graph" {
+        return Err((StatusCode::NOT_FOUND, "only /graph exists".into()));
+    }
+    let query: HashMap<String, String> = req
+        .uri()
+        .query()
+        .map(|q| {
+            url::form_urlencoded::parse(q.as_bytes())
+                .into_owned()
+                .collect()
+        })
+        .unwrap_or_default();
+    let param = |name: &str| query.get(name).map(String::as_str);
+    let decklist = match (req.method(), param("url")) {
+        (&Method::POST, None) => read_body(req.into_body()).await?,
+        (&Method::GET, Some(url)) => moxfield::fetch(url).await.map_err(|e| match &e {
+            Error::Input(io) if io.kind() == io::ErrorKind::InvalidInput => bad_request(e),
+            _ => (StatusCode::BAD_GATEWAY, e.to_string()),
+        })?,
+        _ => {
+            return Err((
+                StatusCode::METHOD_NOT_ALLOWED,
+                "POST a decklist or GET with a moxfield ?url=".into(),
+            ))
+        }
+    };
+    let pod = parse_param::<Pod>(param("pod"))?;
+    let format = parse_param::<OutputFormat>(param("format"))?;
+    if format == OutputFormat::Walkthrough {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "walkthroughs are directories, they can't be served".into(),
+        ));
+    }
+    let layout = parse_param::<Layout>(param("layout"))?;
+    let highlight = param("highlight");
+    with_kind!(pod, |K| {
+        respond::<K>(&decklist, source, format, layout, highlight).await
+    })
+}
+
+fn parse_param<T: ValueEnum + Default>(value: Option<&str>) -> Result<T, (StatusCode, String)> {
+    value.map_or(Ok(T::default()), |v| {
+        T::from_str(v, true).map_err(|e| (StatusCode::BAD_REQUEST, e))
+    })
+}
+
+async fn read_body(mut body: Body) -> Result<String, (StatusCode, String)> {
+    let mut buf = Vec::new();
+    while let Some(chunk) = body.data().await {
+        buf.extend_from_slice(&chunk.map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?);
+        if buf.len() > MAX_BODY {
+            return Err((
+                StatusCode::PAYLOAD_TOO_LARGE,
+                format!("decklists are limited to {MAX_BODY} bytes"),
+            ));
+        }
+    }
+    String::from_utf8(buf).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))
+}
+
+fn bad_request(e: Error) -> (StatusCode, String) {
+    (StatusCode::BAD_REQUEST, e.to_string())
+}
+
+async fn respond<K: PodKind + Clone + Default>(
+    decklist: &str,
+    source: &Source,
+    format: OutputFormat,
+    layout: Layout,
+    highlight: Option<&str>,
+) -> HttpResult {
+    let creatures = decklist::parse(
+        decklist.as_bytes(),
+        source.clone(),
+        InputFormat::Auto,
+        decklist::DEFAULT_CONCURRENCY,
+        (),
+        false,
+        decklist::into_creature,
+    )
+    .await;
+    let (cards, failed) = decklist::collect(creatures.into_stream())
+        .await
+        .map_err(bad_request)?;
+    if !failed.is_empty() {
+        // Scryfall failing isn't the decklist's fault
+        let status = match failed.iter().any(FailedLine::is_network) {
+            true => StatusCode::BAD_GATEWAY,
+            false => StatusCode::UNPROCESSABLE_ENTITY,
+        };
+        return Err((status, Error::Unresolved(failed).to_string()));
+    }
+    let mut builder = PodGraph::<K>::builder().add_cards(cards);
+    if let Some(highlight) = highlight {
+        builder = builder.highlight(highlight);
+    }
+    let graph = builder.build();
+    let body = match format {
+        OutputFormat::Json => serde_json::to_vec(&graph).expect("graphs always serialize"),
+        OutputFormat::Decklist => graph.to_decklist().into_bytes(),
+        OutputFormat::Proxies => proxies::sheet(&proxies::chain(&graph)).into_bytes(),
+        OutputFormat::Markdown => markdown::report(&graph, &RenderOptions::default()).into_bytes(),
+        format => graphviz::render_bytes(
+            graph.to_dot(&RenderOptions::default()).await,
+            format,
+            layout,
+        )
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?,
+    };
+    Ok(Response::builder()
+        .header(CONTENT_TYPE, format.content_type())
+        .body(Body::from(body))
+        .expect("the response is valid"))
+}
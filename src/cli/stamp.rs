@@ -0,0 +1,48 @@
+//! Stamps left next to rendered outputs, so rendering the same graph with
+//! the same options again can be skipped.
+
+use std::path::{Path, PathBuf};
+
+use pyre::{hash::fnv1a, PodGraph, PodKind, RenderOptions};
+use tracing::warn;
+
+use crate::cli::graphviz::{Layout, OutputFormat};
+
+/// Identifies everything that ends up in the output: the cards, edges,
+/// highlight and collection of the graph, the options it's rendered with,
+/// the format and the layout. The build date in the graph's metadata is left
+/// out, or nothing would ever be unchanged.
+pub(crate) fn key<K: PodKind>(
+    graph: &PodGraph<K>,
+    options: &RenderOptions,
+    format: OutputFormat,
+    layout: Layout,
+) -> u64 {
+    let mut json = serde_json::to_value(graph).expect("graphs always serialize");
+    if let Some(metadata) = json.get_mut("metadata").and_then(|m| m.as_object_mut()) {
+        metadata.remove("date");
+    }
+    fnv1a(format!("{json}{options:?}{}{layout:?}", format.extension()).bytes())
+}
+
+fn path(output: &Path) -> PathBuf {
+    let mut name = output.file_name().unwrap_or_default().to_owned();
+    name.push(".hash");
+    output.with_file_name(name)
+}
+
+/// Whether `output` exists and was last rendered from the same `key`.
+pub(crate) async fn is_fresh(output: &Path, key: u64) -> bool {
+    let Ok(stamp) = tokio::fs::read_to_string(path(output)).await else {
+        return false;
+    };
+    stamp.trim() == format!("{key:016x}") && tokio::fs::try_exists(output).await.unwrap_or(false)
+}
+
+/// Records that `output` was rendered from `key`. Failing to is only worth a
+/// warning, the next render just won't be skipped.
+pub(crate) async fn write(output: &Path, key: u64) {
+    if let Err(e) = tokio::fs::write(path(output), format!("{key:016x}\n")).await {
+        warn!("failed to write {}: {e}", path(output).display());
+    }
+}
@@ -0,0 +1,180 @@
+use std::{
+    collections::HashMap,
+    io,
+    path::Path,
+    time::{Duration, SystemTime},
+};
+
+use tracing::{error, info, warn};
+
+use pyre::{
+    error::{self, Error},
+    Card, PodGraph, PodKind,
+};
+
+use crate::{
+    cli::{bookmarks, history, progress::Progress, resolved},
+    read_deck, remove_without, render, report_skipped, restrict, Args, Source,
+};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+async fn modified(path: &Path) -> io::Result<SystemTime> {
+    tokio::fs::metadata(path).await?.modified()
+}
+
+/// Lists each card once, with the counts of the lines that list it summed,
+/// like [PodGraph::add_cards] merges them.
+fn merge_duplicates(cards: Vec<Card>) -> Vec<Card> {
+    let mut unique = Vec::<Card>::new();
+    let mut positions = HashMap::<String, usize>::new();
+    for c in cards {
+        match positions.get(&c.name) {
+            Some(&i) => unique[i].count += c.count,
+            None => {
+                positions.insert(c.name.clone(), unique.len());
+                unique.push(c);
+            }
+        }
+    }
+    unique
+}
+
+/// Applies a new version of the decklist to the graph, removing, updating
+/// and adding only the cards that changed.
+fn update<K: PodKind>(graph: &mut PodGraph<K>, cards: Vec<Card>) {
+    let cards = merge_duplicates(cards);
+    let removed = graph
+        .cards()
+        .filter(|c| !cards.iter().any(|new| new.name == c.name))
+        .map(|c| c.name.clone())
+        .collect::<Vec<_>>();
+    for name in removed {
+        info!(name = %name, "removed");
+        graph.remove_card(&name);
+    }
+    for c in cards {
+        let existing = graph.cards().find(|existing| existing.name == c.name);
+        match existing.map(|existing| *existing == c) {
+            Some(true) => {}
+            Some(false) => {
+                info!(name = %c.name, "updated");
+                graph.update_card(c);
+            }
+            None => {
+                info!(name = %c.name, "added");
+                graph.add_card(c);
+            }
+        }
+    }
+}
+
+/// Polls the decklist for changes and re-renders the graph every time it's
+/// saved. Cards are resolved through the same source and in-memory cache, so
+/// only new cards ever touch the network, and only the cards that changed are
+/// relinked.
+pub(crate) async fn watch<K: PodKind + Clone>(
+    mut graph: PodGraph<K>,
+    args: &Args,
+    source: &Source,
+    progress: &Progress,
+) -> error::Result<()> {
+    let path = args
+        .file
+        .as_deref()
+        .expect("clap requires a file in watch mode");
+    let mut last_modified = modified(path).await.map_err(Error::Input)?;
+    info!("watching {} for changes", path.display());
+    let mut interval = tokio::time::interval(POLL_INTERVAL);
+    loop {
+        interval.tick().await;
+        match modified(path).await {
+            Ok(m) if m == last_modified => continue,
+            Ok(m) => last_modified = m,
+            Err(e) => {
+                warn!("failed to stat {}: {e}", path.display());
+                continue;
+            }
+        }
+        progress.reset();
+        let deck = read_deck(args, source, progress).await;
+        let (deck, failed) = match deck.and_then(|mut d| {
+            let failed = args.tolerate(std::mem::take(&mut d.failed))?;
+            Ok((d, failed))
+        }) {
+            Ok(resolved) => resolved,
+            Err(e) => {
+                error!("{e}");
+                continue;
+            }
+        };
+        if let Some(path) = &args.save_resolved {
+            if let Err(e) = resolved::save(path, &deck.creatures).await {
+                error!("{e}");
+            }
+        }
+        update(&mut graph, args.filter(deck.creatures, &deck.commanders));
+        if let Err(e) = restrict(&mut graph, args) {
+            error!("{e}");
+            continue;
+        }
+        remove_without(&mut graph, args);
+        if let Err(e) = bookmarks::apply(&mut graph, args).await {
+            error!("{e}");
+            continue;
+        }
+        match render(&graph, args).await {
+            Ok(true) => info!("regenerated {}", args.output().display()),
+            Ok(false) => {}
+            Err(e) => error!("{e}"),
+        }
+        history::record(&graph, args).await;
+        report_skipped(failed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pyre::BirthingPod;
+
+    use super::*;
+
+    fn creature(name: &str, cmc: u8, count: u32) -> Card {
+        serde_json::from_value(serde_json::json!({
+            "name": name,
+            "cmc": cmc,
+            "types": ["Creature", "Elf"],
+            "count": count,
+        }))
+        .expect("a valid card")
+    }
+
+    fn counts<K: PodKind>(graph: &PodGraph<K>) -> Vec<(&str, u32)> {
+        graph.cards().map(|c| (c.name.as_str(), c.count)).collect()
+    }
+
+    #[test]
+    fn duplicated_lines_stay_merged() {
+        let decklist = || {
+            vec![
+                creature("Llanowar Elves", 1, 2),
+                creature("Elvish Visionary", 2, 1),
+                creature("Llanowar Elves", 1, 1),
+            ]
+        };
+        let mut graph = PodGraph::<BirthingPod>::new();
+        graph.add_cards(decklist());
+        update(&mut graph, decklist());
+        assert_eq!(
+            counts(&graph),
+            [("Llanowar Elves", 3), ("Elvish Visionary", 1)]
+        );
+        let mut edited = decklist();
+        edited.push(creature("Elvish Visionary", 2, 1));
+        update(&mut graph, edited);
+        assert_eq!(
+            counts(&graph),
+            [("Llanowar Elves", 3), ("Elvish Visionary", 2)]
+        );
+    }
+}
@@ -0,0 +1,99 @@
+//! The cards someone owns, read from a collection export.
+
+use std::{collections::HashSet, io};
+
+use crate::Card;
+
+/// A set of owned card names, matched case insensitively against a card's
+/// full name or the name of any of its faces.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Collection {
+    names: HashSet<String>,
+}
+
+impl<S: AsRef<str>> FromIterator<S> for Collection {
+    fn from_iter<I: IntoIterator<Item = S>>(names: I) -> Self {
+        Self {
+            names: names
+                .into_iter()
+                .map(|n| n.as_ref().trim().to_lowercase())
+                .collect(),
+        }
+    }
+}
+
+/// Splits csv text into rows of fields, handling quoted fields with commas,
+/// doubled quotes and line breaks in them.
+fn csv_rows(text: &str) -> Vec<Vec<String>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => row.push(std::mem::take(&mut field)),
+            '\r' if !quoted => {}
+            '\n' if !quoted => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            c => field.push(c),
+        }
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+    rows
+}
+
+impl Collection {
+    /// Reads a csv collection export, like the ones from Deckbox and
+    /// Moxfield. The header must have a `Name` column. Rows whose `Count`
+    /// column is 0 are skipped.
+    pub fn from_csv(text: &str) -> io::Result<Self> {
+        let mut rows = csv_rows(text).into_iter();
+        let header = rows.next().unwrap_or_default();
+        let column = |name: &str| {
+            header
+                .iter()
+                .position(|h| h.trim().eq_ignore_ascii_case(name))
+        };
+        let name = column("name").ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData, "collection has no Name column")
+        })?;
+        let count = column("count");
+        Ok(rows
+            .filter(|row| count.is_none_or(|i| row.get(i).is_none_or(|c| c.trim() != "0")))
+            .filter_map(|mut row| (name < row.len()).then(|| row.swap_remove(name)))
+            .filter(|name| !name.trim().is_empty())
+            .collect())
+    }
+
+    /// Whether the card is in the collection.
+    pub fn contains(&self, card: &Card) -> bool {
+        let name = card.name.to_lowercase();
+        self.names.contains(&name) || name.split(" // ").any(|face| self.names.contains(face))
+    }
+
+    /// The owned names, lowercased, in no particular order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.names.iter().map(String::as_str)
+    }
+
+    /// How many distinct names are in the collection.
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    /// Whether the collection has no cards.
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+}
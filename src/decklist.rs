@@ -1,118 +1,679 @@
-use std::{collections::HashMap, io, thread::available_parallelism};
+//! Reading decklists and resolving their lines into cards through a
+//! [CardSource].
 
-use futures::{stream::TryStreamExt, Stream};
-use tokio::{
-    fs::File,
-    io::{AsyncBufReadExt, AsyncRead, AsyncWriteExt, BufReader},
-    sync::{OnceCell, RwLock},
+use std::{fmt, io, num::NonZeroUsize, sync::Arc};
+
+use futures::{
+    stream::{self, StreamExt, TryStreamExt},
+    Stream,
 };
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
 use tokio_stream::wrappers::LinesStream;
+use tracing::{debug, info};
+
+use crate::{
+    error::{Error, FailedLine, LineError},
+    source::CardSource,
+    Card,
+};
 
-use crate::Card;
+/// How many lines are looked up at once by default. Lookups that hit the
+/// network are additionally throttled by the [source](CardSource) itself.
+pub const DEFAULT_CONCURRENCY: NonZeroUsize = NonZeroUsize::new(8).unwrap();
 
-fn card_name_trimmer(mut s: &str) -> &str {
-    s = s.trim();
-    if s.chars().next().map(|c| c.is_ascii_digit()) == Some(true) {
-        let mut i = s.chars();
-        i.by_ref()
-            .take_while(|c| c.is_ascii_digit())
-            .for_each(|_| {});
-        s = i.as_str().trim()
-    };
-    s
+/// Gets notified as decklist lines are resolved, e.g. to drive a progress
+/// bar.
+pub trait ResolveProgress: Clone + 'static {
+    /// A line naming a card was read and is about to be resolved.
+    fn queued(&self) {}
+
+    /// A line finished resolving, successfully or not.
+    fn fetched(&self, _name: &str) {}
 }
 
-fn cmc_f32_to_u8(f: f32) -> Option<u8> {
-    let lower = f as u16;
-    let upper = lower + 1;
-    if f > lower as f32 && f < upper as f32 {
-        None
-    } else {
-        lower.try_into().ok()
+impl ResolveProgress for () {}
+
+/// A line of the decklist that names a card.
+#[derive(Debug, Clone)]
+pub struct Line {
+    /// The line as it was written.
+    pub text: String,
+    /// The card name extracted from it.
+    pub name: String,
+    /// Whether the line is in the decklist's commander section.
+    pub commander: bool,
+    /// The tags at the end of the line, like `#recursion`, without the `#`.
+    pub tags: Vec<String>,
+    /// Where the line is in the decklist, counting from 1, or 0 if it didn't
+    /// come from one.
+    pub number: u32,
+    /// The file the decklist was read from, if whoever read it
+    /// [said so](in_file).
+    pub file: Option<Arc<str>>,
+}
+
+impl fmt::Display for Line {
+    /// The line as it was written, prefixed by where it was, like
+    /// `deck.txt:12: "1 Llanowar Elfs"`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.file, self.number) {
+            (_, 0) => write!(f, "{:?}", self.text),
+            (Some(file), n) => write!(f, "{file}:{n}: {:?}", self.text),
+            (None, n) => write!(f, "line {n}: {:?}", self.text),
+        }
     }
 }
 
-type Cache = HashMap<String, Card>;
+/// How a decklist is laid out.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum InputFormat {
+    /// Guess from each line
+    #[default]
+    Auto,
+    /// One card per line, optionally prefixed by a count
+    Plain,
+    /// MTG Arena exports, with set codes and collector numbers
+    Arena,
+    /// Forge and XMage `.dck` files
+    Dck,
+}
 
-static CACHE: OnceCell<RwLock<Cache>> = OnceCell::const_new();
+const SECTION_HEADERS: &[&str] = &[
+    "about",
+    "commander",
+    "companion",
+    "deck",
+    "maybeboard",
+    "sideboard",
+    "tokens",
+];
 
-const CACHE_PATH: &str = "cache.json";
-const CACHE_PATH_TMP: &str = "cache.json.tmp";
+/// Whether a line starts a section: any line ending in `:`, like `Creatures
+/// (12):` or `Ramp:`, or one of the [SECTION_HEADERS] on its own.
+fn is_section_header(line: &str) -> bool {
+    line.ends_with(':')
+        || SECTION_HEADERS
+            .iter()
+            .any(|h| h.eq_ignore_ascii_case(section_name(line)))
+}
 
-async fn cache() -> io::Result<&'static RwLock<HashMap<String, Card>>> {
-    CACHE
-        .get_or_try_init(|| async {
-            let buf = match tokio::fs::read(CACHE_PATH).await {
-                Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Default::default()),
-                r => r,
-            }?;
-            io::Result::Ok(RwLock::new(serde_json::from_slice(&buf)?))
-        })
-        .await
+/// The name of the section a header starts, without its `:` or a count like
+/// the ` (12)` of `Creatures (12):`.
+fn section_name(header: &str) -> &str {
+    let name = header.trim_end_matches(':').trim_end();
+    let counted = name
+        .strip_suffix(')')
+        .and_then(|n| n.rsplit_once(" ("))
+        .filter(|(_, count)| !count.is_empty() && count.chars().all(|c| c.is_ascii_digit()));
+    counted.map_or(name, |(name, _)| name.trim_end())
 }
 
-async fn find_in_cache(name: &str) -> io::Result<Option<Card>> {
-    let cache = cache().await?;
-    Ok(cache.read().await.get(name).cloned())
+/// Whether a name is a token or an emblem, like `Treasure Token` or `Emblem -
+/// Elspeth, Sun's Champion`, which some exports list along with the cards
+/// that make them. Neither is a card that can be looked up.
+fn is_token(name: &str) -> bool {
+    let lower = name.to_lowercase();
+    lower.ends_with(" token") || lower.ends_with(" emblem") || lower.starts_with("emblem ")
 }
 
-async fn store_in_cache(name: &str, card: &Card) -> io::Result<()> {
-    let cache = cache().await?;
-    let mut cache = cache.write().await;
-    cache.insert(name.into(), card.clone());
-    let mut file = File::create(CACHE_PATH_TMP).await?;
-    file.write_all(&serde_json::to_vec::<Cache>(&*cache).unwrap())
-        .await?;
-    tokio::fs::rename(CACHE_PATH_TMP, CACHE_PATH).await
+/// Splits the tags off the end of a line, like `1 Eternal Witness #recursion
+/// #value`. Moxfield's global tags, `#!tag`, lose their `!` too.
+fn split_tags(line: &str) -> (&str, Vec<String>) {
+    let mut parts = line.split(" #");
+    let body = parts.next().unwrap_or_default();
+    let tags = parts
+        .map(|t| t.trim().trim_start_matches('!').to_owned())
+        .filter(|t| !t.is_empty())
+        .collect();
+    (body, tags)
 }
 
-async fn fetch_card(name: &str) -> scryfall::Result<Card> {
-    match find_in_cache(name).await {
-        Ok(Some(card)) => return Ok(card),
-        Err(e) if e.kind() != io::ErrorKind::NotFound => {
-            eprintln!("failed to fetch from cache: {e:?}");
+/// Strips arena's ` (SET) 123` suffix from a card name.
+fn strip_set_suffix(name: &str) -> &str {
+    let Some(i) = name.rfind(" (") else {
+        return name;
+    };
+    let is_suffix = name[i + 2..].split_once(')').is_some_and(|(set, number)| {
+        !set.is_empty()
+            && set.chars().all(|c| c.is_ascii_alphanumeric())
+            && number
+                .trim()
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-')
+    });
+    match is_suffix {
+        true => name[..i].trim_end(),
+        false => name,
+    }
+}
+
+impl InputFormat {
+    /// Extracts the card name from a line, or `None` if the line doesn't name
+    /// a card, including lines that name a [token or emblem](is_token).
+    fn card_name(self, line: &str) -> Option<&str> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with("//") {
+            return None;
         }
-        _ => {
-            eprintln!("cache miss: {name}");
+        let name = match self {
+            InputFormat::Plain => Some(split_quantity(line).1),
+            InputFormat::Dck => dck_card_name(line),
+            InputFormat::Arena | InputFormat::Auto if is_section_header(line) => None,
+            InputFormat::Arena | InputFormat::Auto => {
+                Some(strip_set_suffix(split_quantity(line).1))
+            }
+        };
+        name.filter(|name| !is_token(name))
+    }
+
+    /// The name of the section a line starts, or `None` if it doesn't start
+    /// one.
+    fn section(self, line: &str) -> Option<&str> {
+        let line = line.trim();
+        match self {
+            InputFormat::Plain => None,
+            InputFormat::Arena | InputFormat::Auto => {
+                is_section_header(line).then(|| section_name(line))
+            }
+            InputFormat::Dck => line.strip_prefix('[')?.strip_suffix(']'),
         }
     }
-    let card = scryfall::Card::named_fuzzy(name).await?;
-    let types = card
-        .type_line
-        .map(|t| t.split(' ').map(ToOwned::to_owned).collect())
-        .unwrap_or_default();
 
-    let cmc = cmc_f32_to_u8(
-        card.cmc
-            .unwrap_or_else(|| panic!("{} doens't have cmc", card.name)),
-    )
-    .unwrap_or_else(|| panic!("{} has a fractional cmc", card.name));
-    let card = Card {
-        cmc,
-        name: card.name,
-        types,
+    /// What to tell a user that is about to type a decklist into the
+    /// terminal.
+    pub fn prompt(self) -> &'static str {
+        match self {
+            InputFormat::Auto | InputFormat::Plain => {
+                "paste your decklist, one card per line (e.g. `1 Birthing Pod`), end with Ctrl-D"
+            }
+            InputFormat::Arena => "paste your MTG Arena export, end with Ctrl-D",
+            InputFormat::Dck => "paste your Forge or XMage .dck file, end with Ctrl-D",
+        }
+    }
+}
+
+/// Extracts the card name from a Forge (`1 Name|SET|1`, with `[Section]`
+/// and `Key=Value` metadata lines) or XMage (`1 [SET:1] Name`, with `SB: `
+/// sideboard prefixes and `NAME:`/`LAYOUT` lines) `.dck` line.
+fn dck_card_name(line: &str) -> Option<&str> {
+    let starts_with_count = line.starts_with(|c: char| c.is_ascii_digit());
+    let is_metadata = (line.starts_with('[') && line.ends_with(']'))
+        || (line.contains('=') && !starts_with_count)
+        || line.starts_with("NAME:")
+        || line.starts_with("LAYOUT ");
+    if is_metadata {
+        return None;
+    }
+    let mut name = split_count(line.strip_prefix("SB:").unwrap_or(line)).1;
+    if name.starts_with('[') {
+        name = name.split_once(']').map_or(name, |(_, n)| n.trim());
+    }
+    Some(name.split_once('|').map_or(name, |(n, _)| n.trim_end()))
+}
+
+/// The count a line gives, like the 2 of `2 Llanowar Elves`, or 1 if it has
+/// none.
+fn line_count(line: &str) -> u32 {
+    let line = split_tags(line).0.trim();
+    split_quantity(line.strip_prefix("SB:").unwrap_or(line))
+        .0
+        .unwrap_or(1)
+}
+
+/// The value of an ascii or full width digit.
+fn digit(c: char) -> Option<u32> {
+    match c {
+        '0'..='9' => c.to_digit(10),
+        '０'..='９' => Some(c as u32 - '０' as u32),
+        _ => None,
+    }
+}
+
+/// Splits the quantity off a line like [split_count], but also off its end,
+/// like `Card x4`, and out of tab separated columns, like `4\tCard\tM19`,
+/// where the name is the first column that isn't only a quantity.
+fn split_quantity(s: &str) -> (Option<u32>, &str) {
+    let s = s.trim();
+    if s.contains('\t') {
+        let (mut count, mut name) = (None, None);
+        for column in s.split('\t').filter(|c| !c.trim().is_empty()) {
+            match split_count(column) {
+                (Some(n), "") => count = count.or(Some(n)),
+                (n, rest) => {
+                    count = count.or(n);
+                    name = name.or(Some(rest));
+                }
+            }
+        }
+        if let Some(name) = name {
+            return (count, name);
+        }
+    }
+    match split_count(s) {
+        (None, rest) => {
+            let suffix = rest.rsplit_once(' ').and_then(|(name, quantity)| {
+                let digits = quantity
+                    .strip_prefix(['x', 'X', '×'])
+                    .or_else(|| quantity.strip_suffix(['x', 'X', '×']))?;
+                match split_count(digits) {
+                    (Some(n), "") if !digits.starts_with(['x', 'X']) => {
+                        Some((Some(n), name.trim_end()))
+                    }
+                    _ => None,
+                }
+            });
+            suffix.unwrap_or((None, rest))
+        }
+        counted => counted,
+    }
+}
+
+/// Splits the quantity off the start of a line, like `4 Card`, `4x Card`,
+/// `x4 Card` or `４ Card`, returning it and the rest of the line.
+fn split_count(s: &str) -> (Option<u32>, &str) {
+    let s = s.trim();
+    let (rest, x_first) = match s.strip_prefix(['x', 'X']) {
+        Some(rest) if rest.starts_with(|c| digit(c).is_some()) => (rest, true),
+        _ => (s, false),
     };
-    if let Err(e) = store_in_cache(name, &card).await {
-        eprintln!("failed to store in cache: {e:?}");
+    let end = rest.find(|c| digit(c).is_none()).unwrap_or(rest.len());
+    if end == 0 {
+        return (None, s);
     }
-    Ok(card)
+    let count = rest[..end]
+        .chars()
+        .filter_map(digit)
+        .fold(0u32, |n, d| n.saturating_mul(10).saturating_add(d));
+    let mut rest = &rest[end..];
+    if !x_first {
+        rest = match rest.strip_prefix(['x', 'X']) {
+            Some(after) if after.is_empty() || after.starts_with(char::is_whitespace) => after,
+            _ => rest,
+        };
+    }
+    (Some(count), rest.trim())
+}
+
+/// Keeps only creatures, with the `—` dropped from their type line.
+pub fn into_creature(mut card: Card) -> Option<Card> {
+    (card.types.iter().any(|t| &**t == "Creature")).then(|| {
+        card.types.retain(|t| &**t != "—");
+        card
+    })
 }
 
-pub(super) async fn parse<'r, R: AsyncRead + 'r>(
+/// Resolves every non blank line of the decklist through `source`, pairing
+/// each line with the outcome of its lookup. Nothing is filtered and failed
+/// lookups don't end the stream. Up to `concurrency` lines are looked up at
+/// once, but they are yielded in decklist order, so the same decklist always
+/// builds the same graph. Tokens and emblems, and everything in a `Tokens`
+/// section, aren't looked up at all.
+///
+/// The whole decklist is read before anything is resolved, so that `source`
+/// can [prefetch](CardSource::prefetch) every name at once. If reading fails
+/// the error is yielded after the lines read until then.
+pub async fn resolve<'r, R, S, P>(
     r: R,
-) -> impl Stream<Item = scryfall::Result<Card>> + 'r {
-    let reader = BufReader::new(r);
-    LinesStream::new(reader.lines())
-        .map_err(scryfall::Error::from)
-        .map_ok(|line| async move {
-            let mut card = fetch_card(card_name_trimmer(&line)).await?;
-            Ok((card.types.iter().any(|t| t == "Creature")).then(|| {
-                if let Some(dash) = card.types.iter().position(|s| s == "—") {
-                    card.types.drain(..=dash).for_each(|_| {});
+    source: S,
+    format: InputFormat,
+    concurrency: NonZeroUsize,
+    progress: P,
+) -> impl Stream<Item = io::Result<(Line, scryfall::Result<Card>)>> + 'r
+where
+    R: AsyncRead + 'r,
+    S: CardSource + Clone + 'r,
+    P: ResolveProgress,
+{
+    let mut lines = Vec::new();
+    let mut commander = false;
+    let mut tokens = false;
+    let mut number = 0;
+    let mut texts = std::pin::pin!(LinesStream::new(BufReader::new(r).lines()));
+    let error = loop {
+        match texts.next().await {
+            Some(Ok(text)) => {
+                number += 1;
+                if let Some(section) = format.section(&text) {
+                    commander = section.eq_ignore_ascii_case("commander");
+                    tokens = section.eq_ignore_ascii_case("tokens");
                 }
-                card
+                if tokens {
+                    continue;
+                }
+                let (body, tags) = split_tags(&text);
+                lines.extend(format.card_name(body).map(|name| Line {
+                    name: name.to_owned(),
+                    text: text.clone(),
+                    commander,
+                    tags,
+                    number,
+                    file: None,
+                }))
+            }
+            Some(Err(e)) => break Some(e),
+            None => break None,
+        }
+    };
+    let names = lines.iter().map(|l| l.name.as_str()).collect::<Vec<_>>();
+    source.prefetch(&names).await;
+    stream::iter(lines)
+        .map(move |line| {
+            let progress = progress.clone();
+            let source = source.clone();
+            progress.queued();
+            async move {
+                let card = source.resolve(&line.name).await;
+                progress.fetched(card.as_ref().map_or(&line.name, |c| &c.name));
+                Ok((line, card))
+            }
+        })
+        .buffered(concurrency.get())
+        .chain(stream::iter(error.map(Err)))
+}
+
+/// Whether looking up this line resolved to a card with a different name,
+/// which can mean a typo silently resolved to the wrong card.
+pub fn is_fuzzy_match(line: &Line, card: &Card) -> bool {
+    !card
+        .name
+        .split(" // ")
+        .chain([card.name.as_str()])
+        .any(|name| name.eq_ignore_ascii_case(&line.name))
+}
+
+fn check_name(line: &Line, card: Card, strict_names: bool) -> Result<Card, LineError> {
+    if is_fuzzy_match(line, &card) {
+        if strict_names {
+            return Err(LineError::NameMismatch(card.name));
+        }
+        info!("{:?} -> {:?}", line.name, card.name);
+    }
+    Ok(card)
+}
+
+/// Resolves the decklist into the cards `filter` keeps, usually
+/// [into_creature]. The filter may also rewrite the cards it keeps. Cards in
+/// the commander section are marked as [commanders](Card::commander) before
+/// they are filtered. Lines that fail to resolve are yielded as [Error::Line]
+/// without ending the stream.
+pub async fn parse<'r, R, S, P, F>(
+    r: R,
+    source: S,
+    format: InputFormat,
+    concurrency: NonZeroUsize,
+    progress: P,
+    strict_names: bool,
+    mut filter: F,
+) -> impl Stream<Item = crate::error::Result<Card>> + 'r
+where
+    R: AsyncRead + 'r,
+    S: CardSource + Clone + 'r,
+    P: ResolveProgress,
+    F: FnMut(Card) -> Option<Card> + 'r,
+{
+    resolve(r, source, format, concurrency, progress)
+        .await
+        .map_err(Error::Input)
+        .and_then(move |(line, card)| {
+            let card = card
+                .map_err(LineError::from)
+                .and_then(|card| check_name(&line, card, strict_names))
+                .map(|card| Card {
+                    commander: line.commander,
+                    tags: line.tags.clone(),
+                    count: line_count(&line.text),
+                    ..card
+                });
+            futures::future::ready(card.map_err(|error| Error::Line(FailedLine { line, error })))
+        })
+        .try_filter_map(move |card| futures::future::ready(Ok(filter(card))))
+}
+
+/// Records that the lines of a stream from [parse] were read from `file`, so
+/// that the lines that fail to resolve say where they are.
+pub fn in_file<'r, S>(cards: S, file: &str) -> impl Stream<Item = crate::error::Result<Card>> + 'r
+where
+    S: Stream<Item = crate::error::Result<Card>> + 'r,
+{
+    let file = Arc::<str>::from(file);
+    cards.map_err(move |e| match e {
+        Error::Line(mut failed) => {
+            failed.line.file = Some(file.clone());
+            Error::Line(failed)
+        }
+        e => e,
+    })
+}
+
+/// Drains a stream of cards from [parse], separating the cards from the lines
+/// that failed to resolve. Any error other than [Error::Line] is returned
+/// immediately.
+pub async fn collect<S>(cards: S) -> crate::error::Result<(Vec<Card>, Vec<FailedLine>)>
+where
+    S: Stream<Item = crate::error::Result<Card>>,
+{
+    let mut cards = std::pin::pin!(cards);
+    let mut resolved = Vec::new();
+    let mut failed = Vec::new();
+    while let Some(card) = cards.next().await {
+        match card {
+            Ok(card) => resolved.push(card),
+            Err(Error::Line(line)) => {
+                debug!("failed to resolve {line}");
+                failed.push(line);
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    Ok((resolved, failed))
+}
+
+#[cfg(test)]
+mod tests {
+    use async_trait::async_trait;
+    use futures::{executor::block_on, TryStreamExt};
+
+    use super::*;
+
+    /// Resolves every name to a creature of that name.
+    #[derive(Clone)]
+    struct Names;
+
+    #[async_trait]
+    impl CardSource for Names {
+        async fn resolve(&self, name: &str) -> scryfall::Result<Card> {
+            Ok(serde_json::from_value(serde_json::json!({
+                "name": name,
+                "cmc": 1,
+                "types": ["Creature", "—", "Elf"],
             }))
+            .expect("a valid card"))
+        }
+    }
+
+    /// The lines of the decklist that name a card, as parsed from `format`.
+    fn lines(decklist: &str, format: InputFormat) -> Vec<Line> {
+        block_on(async {
+            resolve(decklist.as_bytes(), Names, format, DEFAULT_CONCURRENCY, ())
+                .await
+                .map_ok(|(line, _)| line)
+                .try_collect()
+                .await
+                .expect("reading from memory never fails")
         })
-        .try_buffer_unordered(available_parallelism().unwrap().get())
-        .try_filter_map(|r| futures::future::ready(Ok(r)))
+    }
+
+    fn names(lines: &[Line]) -> Vec<&str> {
+        lines.iter().map(|l| l.name.as_str()).collect()
+    }
+
+    #[test]
+    fn section_headers_are_not_cards() {
+        let decklist = "\
+Commander (1):
+1 Prossh, Skyraider of Kher
+Creatures (2):
+1 Llanowar Elves
+1 Elvish Mystic
+Lands:
+1 Forest
+Ramp:
+1 Sol Ring
+Sideboard
+1 Fog
+";
+        for format in [InputFormat::Auto, InputFormat::Arena] {
+            let lines = lines(decklist, format);
+            assert_eq!(
+                names(&lines),
+                [
+                    "Prossh, Skyraider of Kher",
+                    "Llanowar Elves",
+                    "Elvish Mystic",
+                    "Forest",
+                    "Sol Ring",
+                    "Fog",
+                ]
+            );
+            let commanders = lines
+                .iter()
+                .filter(|l| l.commander)
+                .map(|l| l.name.as_str())
+                .collect::<Vec<_>>();
+            assert_eq!(commanders, ["Prossh, Skyraider of Kher"]);
+        }
+    }
+
+    #[test]
+    fn quantities_before_the_name() {
+        for line in [
+            "4 Llanowar Elves",
+            "4x Llanowar Elves",
+            "4X Llanowar Elves",
+            "x4 Llanowar Elves",
+            "４ Llanowar Elves",
+            "  4x   Llanowar Elves  ",
+        ] {
+            assert_eq!(
+                split_quantity(line),
+                (Some(4), "Llanowar Elves"),
+                "{line:?}"
+            );
+        }
+        assert_eq!(split_quantity("１２ Forest"), (Some(12), "Forest"));
+    }
+
+    #[test]
+    fn quantities_after_the_name() {
+        for line in [
+            "Llanowar Elves x4",
+            "Llanowar Elves X4",
+            "Llanowar Elves 4x",
+            "Llanowar Elves ×4",
+            "Llanowar Elves x４",
+        ] {
+            assert_eq!(
+                split_quantity(line),
+                (Some(4), "Llanowar Elves"),
+                "{line:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn no_quantity() {
+        assert_eq!(split_quantity("Llanowar Elves"), (None, "Llanowar Elves"));
+        assert_eq!(
+            split_quantity("Xenagos, the Reveler"),
+            (None, "Xenagos, the Reveler")
+        );
+        assert_eq!(split_count("x Llanowar Elves"), (None, "x Llanowar Elves"));
+    }
+
+    #[test]
+    fn names_starting_or_ending_with_digits() {
+        assert_eq!(
+            split_quantity("1 1996 World Champion"),
+            (Some(1), "1996 World Champion")
+        );
+        assert_eq!(
+            split_quantity("4x 1996 World Champion"),
+            (Some(4), "1996 World Champion")
+        );
+        assert_eq!(split_quantity("1 +2 Mace"), (Some(1), "+2 Mace"));
+        assert_eq!(
+            split_quantity("1 Borrowing 100,000 Arrows"),
+            (Some(1), "Borrowing 100,000 Arrows")
+        );
+        assert_eq!(
+            split_quantity("2 Hazoret's Monument"),
+            (Some(2), "Hazoret's Monument")
+        );
+    }
+
+    #[test]
+    fn tab_separated_exports() {
+        assert_eq!(
+            split_quantity("4\tLlanowar Elves\tM19"),
+            (Some(4), "Llanowar Elves")
+        );
+        assert_eq!(
+            split_quantity("Llanowar Elves\t4"),
+            (Some(4), "Llanowar Elves")
+        );
+        assert_eq!(
+            split_quantity("\t4x\tLlanowar Elves\t"),
+            (Some(4), "Llanowar Elves")
+        );
+        assert_eq!(line_count("4\tLlanowar Elves\tM19"), 4);
+    }
+
+    #[test]
+    fn arena_lines() {
+        let line = "4 Llanowar Elves (M19) 314";
+        for format in [InputFormat::Arena, InputFormat::Auto] {
+            assert_eq!(format.card_name(line), Some("Llanowar Elves"));
+        }
+        assert_eq!(line_count(line), 4);
+        assert_eq!(
+            InputFormat::Arena.card_name("1 Fire // Ice (MH2) 290"),
+            Some("Fire // Ice")
+        );
+        assert_eq!(
+            InputFormat::Arena.card_name("1 Erase (Not the Urza's Legacy One)"),
+            Some("Erase (Not the Urza's Legacy One)")
+        );
+    }
+
+    #[test]
+    fn dck_lines() {
+        let dck = InputFormat::Dck;
+        assert_eq!(
+            dck.card_name("4 Llanowar Elves|M19|1"),
+            Some("Llanowar Elves")
+        );
+        assert_eq!(
+            dck.card_name("4 [M19:314] Llanowar Elves"),
+            Some("Llanowar Elves")
+        );
+        assert_eq!(dck.card_name("SB: 1 [M19:1] Fog"), Some("Fog"));
+        assert_eq!(line_count("SB: 2 [M19:1] Fog"), 2);
+        for metadata in [
+            "[metadata]",
+            "Name=Elves",
+            "NAME:Elves",
+            "LAYOUT MAIN:(1,1)(NONE,false,50)",
+        ] {
+            assert_eq!(dck.card_name(metadata), None, "{metadata:?}");
+        }
+    }
+
+    #[test]
+    fn section_names_drop_colons_and_counts() {
+        assert_eq!(section_name("Creatures (12):"), "Creatures");
+        assert_eq!(section_name("Commander"), "Commander");
+        assert_eq!(section_name("Ramp:"), "Ramp");
+        assert_eq!(section_name("Deck (Main)"), "Deck (Main)");
+    }
 }
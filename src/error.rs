@@ -0,0 +1,101 @@
+//! Errors produced while resolving decklists and writing graphs.
+
+use std::{fmt, io};
+
+use crate::decklist::Line;
+
+/// Why a decklist line couldn't be resolved.
+#[derive(Debug)]
+pub enum LineError {
+    /// Scryfall couldn't resolve the card.
+    Lookup(Box<scryfall::Error>),
+    /// The fuzzy lookup resolved to a card with a different name, and strict
+    /// name matching was requested.
+    NameMismatch(String),
+}
+
+impl From<scryfall::Error> for LineError {
+    fn from(e: scryfall::Error) -> Self {
+        Self::Lookup(Box::new(e))
+    }
+}
+
+impl fmt::Display for LineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LineError::Lookup(e) => e.fmt(f),
+            LineError::NameMismatch(name) => write!(f, "fuzzy matched {name:?}"),
+        }
+    }
+}
+
+/// A decklist line that couldn't be resolved to a card.
+#[derive(Debug)]
+pub struct FailedLine {
+    /// The line that failed.
+    pub line: Line,
+    /// Why it failed.
+    pub error: LineError,
+}
+
+impl FailedLine {
+    /// Whether resolving the line failed because of the network, rather than
+    /// because of the line itself.
+    pub fn is_network(&self) -> bool {
+        matches!(
+            &self.error,
+            LineError::Lookup(e)
+                if matches!(**e, scryfall::Error::ReqwestError(..) | scryfall::Error::IoError(_))
+        )
+    }
+}
+
+impl fmt::Display for FailedLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.line, self.error)
+    }
+}
+
+/// Everything that can go wrong between reading a decklist and writing its
+/// graph.
+#[derive(Debug)]
+pub enum Error {
+    /// A single line failed to resolve. Collected into [Error::Unresolved] by
+    /// whoever consumes the card stream.
+    Line(FailedLine),
+    /// Some of the decklist's lines failed to resolve.
+    Unresolved(Vec<FailedLine>),
+    /// The decklist couldn't be read.
+    Input(io::Error),
+    /// None of the decklist's cards made it into the graph.
+    NoCreatures {
+        /// How many of the decklist's cards were dropped for not being
+        /// creatures.
+        non_creatures: usize,
+    },
+    /// The output couldn't be written.
+    Output(io::Error),
+}
+
+/// Result type of this crate.
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Line(l) => write!(f, "failed to resolve {l}"),
+            Error::Unresolved(lines) => {
+                write!(f, "failed to resolve {} lines:", lines.len())?;
+                lines.iter().try_for_each(|l| write!(f, "\n    {l}"))
+            }
+            Error::Input(e) => write!(f, "failed to read decklist: {e}"),
+            Error::NoCreatures { non_creatures } => write!(
+                f,
+                "no creatures to graph, {non_creatures} cards were dropped for not being creatures"
+            ),
+            Error::Output(e) => write!(f, "failed to write output: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
@@ -0,0 +1,126 @@
+//! A C interface, building graphs out of decklists in memory.
+//!
+//! Strings returned by these functions are owned by the caller and must be
+//! released with [pyre_free_string]. When a function returns `NULL`,
+//! [pyre_last_error] describes what went wrong.
+
+use std::{
+    cell::RefCell,
+    ffi::{c_char, CStr, CString},
+    ptr,
+};
+
+use crate::{
+    blocking::block_on, BirthingPod, InputFormat, PodGraph, PodKind, ProliferatePod, PyreOfHeroes,
+    RenderOptions, Transmute, Vannifar, XSpell,
+};
+
+/// Selects [BirthingPod] in the `pod` argument.
+pub const PYRE_BIRTHING_POD: u32 = 0;
+/// Selects [PyreOfHeroes] in the `pod` argument.
+pub const PYRE_PYRE_OF_HEROES: u32 = 1;
+/// Selects [Vannifar] in the `pod` argument.
+pub const PYRE_VANNIFAR: u32 = 2;
+/// Selects [Transmute] in the `pod` argument.
+pub const PYRE_TRANSMUTE: u32 = 3;
+/// Selects [ProliferatePod] in the `pod` argument.
+pub const PYRE_PROLIFERATE_POD: u32 = 4;
+/// Selects [XSpell] in the `pod` argument.
+pub const PYRE_X_SPELL: u32 = 5;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(e: impl ToString) {
+    let e = CString::new(e.to_string().replace('\0', "")).expect("nul bytes were removed");
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(e));
+}
+
+fn build<K: PodKind + Default>(decklist: &str, json: bool) -> Result<String, String> {
+    let graph =
+        block_on(PodGraph::<K>::builder().add_decklist(decklist.as_bytes(), InputFormat::Auto))
+            .map_err(|e| e.to_string())?
+            .build();
+    if json {
+        serde_json::to_string(&graph).map_err(|e| e.to_string())
+    } else {
+        Ok(block_on(graph.to_dot(&RenderOptions::default())))
+    }
+}
+
+/// # Safety
+/// `decklist` must be `NULL` or a valid nul terminated string.
+unsafe fn build_ffi(decklist: *const c_char, pod: u32, json: bool) -> *mut c_char {
+    if decklist.is_null() {
+        set_last_error("decklist is NULL");
+        return ptr::null_mut();
+    }
+    let decklist = match CStr::from_ptr(decklist).to_str() {
+        Ok(d) => d,
+        Err(e) => {
+            set_last_error(format!("decklist is not valid utf8: {e}"));
+            return ptr::null_mut();
+        }
+    };
+    let built = std::panic::catch_unwind(|| match pod {
+        PYRE_BIRTHING_POD => build::<BirthingPod>(decklist, json),
+        PYRE_PYRE_OF_HEROES => build::<PyreOfHeroes>(decklist, json),
+        PYRE_VANNIFAR => build::<Vannifar>(decklist, json),
+        PYRE_TRANSMUTE => build::<Transmute>(decklist, json),
+        PYRE_PROLIFERATE_POD => build::<ProliferatePod>(decklist, json),
+        PYRE_X_SPELL => build::<XSpell>(decklist, json),
+        _ => Err(format!("unknown pod kind: {pod}")),
+    })
+    .unwrap_or_else(|_| Err("panicked while building the graph".into()));
+    match built.map(CString::new) {
+        Ok(Ok(out)) => out.into_raw(),
+        Ok(Err(e)) => {
+            set_last_error(e);
+            ptr::null_mut()
+        }
+        Err(e) => {
+            set_last_error(e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Resolves `decklist` through Scryfall and returns its graph as graphviz
+/// source, or `NULL` on failure.
+///
+/// # Safety
+/// `decklist` must be `NULL` or a valid nul terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn pyre_build_dot(decklist: *const c_char, pod: u32) -> *mut c_char {
+    build_ffi(decklist, pod, false)
+}
+
+/// Resolves `decklist` through Scryfall and returns its graph as json, or
+/// `NULL` on failure.
+///
+/// # Safety
+/// `decklist` must be `NULL` or a valid nul terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn pyre_build_json(decklist: *const c_char, pod: u32) -> *mut c_char {
+    build_ffi(decklist, pod, true)
+}
+
+/// The error of the last call on this thread that returned `NULL`, or `NULL`
+/// if there was none. Valid until the next failing call on this thread.
+#[no_mangle]
+pub extern "C" fn pyre_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(ptr::null(), |e| e.as_ptr()))
+}
+
+/// Releases a string returned by this library.
+///
+/// # Safety
+/// `s` must be `NULL` or a string returned by this library that wasn't freed
+/// yet.
+#[no_mangle]
+pub unsafe extern "C" fn pyre_free_string(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
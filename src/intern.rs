@@ -0,0 +1,37 @@
+//! Interning of the strings that repeat across many cards, like creature
+//! types, so that each is allocated once and equal strings share a pointer.
+
+use std::{
+    collections::HashSet,
+    sync::{Arc, Mutex, OnceLock},
+};
+
+use serde::{Deserialize, Deserializer};
+
+static STRINGS: OnceLock<Mutex<HashSet<Arc<str>>>> = OnceLock::new();
+
+/// The shared copy of `s`.
+pub(crate) fn intern(s: &str) -> Arc<str> {
+    let mut strings = STRINGS
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    match strings.get(s) {
+        Some(interned) => interned.clone(),
+        None => {
+            let interned = Arc::<str>::from(s);
+            strings.insert(interned.clone());
+            interned
+        }
+    }
+}
+
+/// Deserializes a list of strings, interning each of them.
+pub(crate) fn deserialize_all<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<Arc<str>>, D::Error> {
+    Ok(Vec::<String>::deserialize(deserializer)?
+        .iter()
+        .map(|s| intern(s))
+        .collect())
+}
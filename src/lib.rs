@@ -0,0 +1,157 @@
+//! Builds graphs of how creatures in a decklist can tutor each other through
+//! engines like Birthing Pod or Pyre of Heroes.
+//!
+//! The usual flow is to resolve a decklist into [Card]s from a [CardSource]
+//! with [decklist::parse], insert them into a [PodGraph] parameterized by the
+//! [PodKind] of the engine being analysed and then render it with
+//! [PodGraph::to_img]. [PodGraphBuilder] does all of this in one go, and with
+//! the `blocking` feature so does `blocking::build_graph`, without an async
+//! runtime.
+
+#[cfg(feature = "blocking")]
+pub mod blocking;
+mod builder;
+pub mod collection;
+pub mod decklist;
+pub mod error;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod hash;
+mod intern;
+pub mod moxfield;
+pub mod pyre_graph;
+pub mod source;
+
+use std::{collections::HashMap, sync::Arc};
+
+use scryfall::{
+    card::{Color, Legality},
+    format::Format,
+};
+use serde::{Deserialize, Serialize};
+
+pub use builder::PodGraphBuilder;
+pub use collection::Collection;
+pub use decklist::{InputFormat, ResolveProgress};
+pub use error::{Error, FailedLine, LineError};
+pub use pyre_graph::{
+    BirthingPod, ClusterBy, EdgeFilter, ManaCurve, MatchOn, Metadata, NodeShapes, PodEdge,
+    PodGraph, PodKind, ProliferatePod, PyreOfHeroes, Redundancy, RenderOptions, Rung, Transmute,
+    Vannifar, WithinIdentity, XSpell,
+};
+pub use source::CardSource;
+
+/// The parts of a Scryfall card the graphs care about.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Card {
+    /// The card's canonical name.
+    pub name: String,
+    /// The card's mana value.
+    pub cmc: u8,
+    /// The words of the card's type line, interned so that cards share them.
+    /// For creatures kept by [decklist::into_creature] the `—` is dropped.
+    #[serde(deserialize_with = "intern::deserialize_all")]
+    pub types: Vec<Arc<str>>,
+    /// The card's legality in each format, empty for cards cached before
+    /// legalities were recorded.
+    #[serde(default)]
+    pub legalities: HashMap<Format, Legality>,
+    /// The card's color identity, empty for cards cached before identities
+    /// were recorded.
+    #[serde(default)]
+    pub color_identity: Vec<Color>,
+    /// The card's rules text, faces separated by a `//` line, empty for cards
+    /// cached before rules text was recorded.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub oracle_text: String,
+    /// The card's power as printed, like `2` or `*`, of its front face for
+    /// cards with several. `None` for cards without one and cards cached
+    /// before it was recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub power: Option<String>,
+    /// The card's toughness, like [power](Self::power).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub toughness: Option<String>,
+    /// Whether the decklist lists the card as one of its commanders. Set by
+    /// [decklist::parse] from the section the card is in, never by a
+    /// [CardSource].
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub commander: bool,
+    /// The tags the decklist gives the card, like `#recursion`, without the
+    /// `#`. Set by [decklist::parse], never by a [CardSource].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// How many copies the decklist has, summed over the lines that list the
+    /// card. Set by [decklist::parse], never by a [CardSource].
+    #[serde(default = "one", skip_serializing_if = "is_one")]
+    pub count: u32,
+}
+
+fn one() -> u32 {
+    1
+}
+
+fn is_one(n: &u32) -> bool {
+    *n == 1
+}
+
+/// The words of a type line that aren't subtypes: supertypes, card types and
+/// the separators between them and between faces.
+const NOT_SUBTYPES: [&str; 24] = [
+    "—",
+    "//",
+    "Basic",
+    "Legendary",
+    "Ongoing",
+    "Snow",
+    "World",
+    "Artifact",
+    "Battle",
+    "Conspiracy",
+    "Creature",
+    "Dungeon",
+    "Emblem",
+    "Enchantment",
+    "Instant",
+    "Kindred",
+    "Land",
+    "Phenomenon",
+    "Plane",
+    "Planeswalker",
+    "Scheme",
+    "Sorcery",
+    "Tribal",
+    "Vanguard",
+];
+
+impl Card {
+    /// The subtypes among the card's [types](Self::types), of every face.
+    pub fn subtypes(&self) -> impl Iterator<Item = &Arc<str>> {
+        self.types.iter().filter(|t| !NOT_SUBTYPES.contains(&&***t))
+    }
+
+    /// Whether the card has every creature type, through changeling or like
+    /// Mistform Ultimus. Read from the [rules text](Self::oracle_text), so
+    /// it's never the case for cards cached before it was recorded.
+    pub fn is_every_creature_type(&self) -> bool {
+        let itself = format!("{} is every creature type", self.name);
+        self.oracle_text
+            .lines()
+            .any(|l| l.starts_with("Changeling") || l.contains(&itself))
+    }
+
+    /// Whether the card's color identity is within `identity`.
+    pub fn within_identity(&self, identity: &[Color]) -> bool {
+        self.color_identity.iter().all(|c| identity.contains(c))
+    }
+
+    /// The card's page on Scryfall, through an exact name search, which
+    /// Scryfall redirects to the card when there's a single match.
+    pub fn scryfall_url(&self) -> String {
+        let query = format!("!\"{}\"", self.name);
+        format!(
+            "https://scryfall.com/search?q={}",
+            url::form_urlencoded::byte_serialize(query.as_bytes()).collect::<String>()
+        )
+    }
+}
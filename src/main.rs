@@ -1,47 +1,1233 @@
-mod decklist;
-mod pyre_graph;
+mod cli;
 
-use std::{path::PathBuf, pin::Pin};
+use std::{
+    collections::{BTreeSet, HashSet},
+    fmt::Debug,
+    io::IsTerminal,
+    num::NonZeroUsize,
+    path::PathBuf,
+    pin::Pin,
+    process::ExitCode,
+    str::FromStr,
+    sync::Arc,
+};
 
-use clap::Parser;
-use futures::{Stream, StreamExt, TryStreamExt};
-use serde::{Deserialize, Serialize};
-use tokio::{fs::File, io::stdin};
+use clap::{ArgAction, CommandFactory, Parser, Subcommand, ValueEnum};
+use cli::{
+    bookmarks, census, check, checkpoint, cube,
+    engine::{self, Engine},
+    events, fixes, graph_cache, graphviz,
+    graphviz::{Layout, OutputFormat},
+    hands, history,
+    logging::{self, LogFormat, Verbosity},
+    markdown,
+    progress::Progress,
+    proxies, report,
+    require::Requirement,
+    resolved, serve, stamp, watch, EXIT_CODES_HELP,
+};
+use futures::{future, Stream, StreamExt, TryStreamExt};
+use pyre::{
+    decklist::{self, Line},
+    error,
+    source::{BulkData, CacheOnly, Mtgjson, Scryfall},
+    Card, CardSource, ClusterBy, Collection, EdgeFilter, Error, FailedLine, InputFormat, MatchOn,
+    Metadata, NodeShapes, PodGraph, PodGraphBuilder, PodKind, PyreOfHeroes, RenderOptions,
+    WithinIdentity,
+};
+use scryfall::{card::Color, format::Format};
+use tokio::{
+    fs::File,
+    io::{stdin, AsyncRead},
+};
+use tracing::{debug, error, info, warn};
 
 #[derive(Parser)]
+#[command(args_conflicts_with_subcommands = true, after_help = EXIT_CODES_HELP)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
     file: Option<PathBuf>,
+    /// Read the decklist from the clipboard instead of a file
+    #[cfg(feature = "clipboard")]
+    #[arg(long, conflicts_with_all = ["file", "watch"])]
+    from_clipboard: bool,
+    /// Put the output on the clipboard once it's written. Only works for
+    /// text formats, like dot or markdown
+    #[cfg(feature = "clipboard")]
+    #[arg(long, conflicts_with = "split_by_cmc")]
+    to_clipboard: bool,
     #[arg(short = 't', long)]
     highlight: Option<String>,
+    /// Only highlight the card named exactly like --highlight, instead of
+    /// the first card whose name contains it
+    #[arg(long, requires = "highlight")]
+    highlight_exact: bool,
+    /// Fail instead of warning when --highlight matches no card
+    #[arg(long, requires = "highlight")]
+    strict: bool,
+    /// Save the chain to the highlighted card, or the longest chain, under
+    /// this name next to the decklist, like `deck.chains.json` for `deck.txt`
+    #[arg(long, requires = "file")]
+    save_chain: Option<String>,
+    /// Highlight a chain saved with --save-chain, for as long as its cards
+    /// are in the decklist
+    #[arg(long, requires = "file", conflicts_with_all = ["highlight", "save_chain"])]
+    chain: Option<String>,
+    /// Don't draw edges with this label, e.g. a creature type in
+    /// pyre-of-heroes mode. Can be repeated
+    #[arg(long)]
+    hide_edges_with_label: Vec<String>,
+    /// Only draw edges with this label. Can be repeated
+    #[arg(long)]
+    only_edges_with_label: Vec<String>,
+    /// How to group the cards in the rendered graph. Tags are written at the
+    /// end of decklist lines, like `1 Eternal Witness #recursion`
+    #[arg(long, value_enum, default_value_t)]
+    cluster_by: ClusterBy,
+    /// Label the rendered graph with its card, edge and orphan counts and the
+    /// length of its longest chain
+    #[arg(long)]
+    stats_footer: bool,
+    /// Draw cards of a type with a graphviz shape, like `enchantment=hexagon`,
+    /// or with a second border with `legendary=double`. Can be repeated, and
+    /// overrides the defaults, `artifact=box` and `legendary=double`
+    #[arg(long, value_name = "TYPE=SHAPE", value_parser = parse_node_shape)]
+    node_shape: Vec<(String, String)>,
+    /// Only keep the cards connected to this one, directly or not
+    #[arg(long)]
+    component_of: Option<String>,
+    /// Leave this card out, as if it had been removed, and report which
+    /// payoffs nothing climbs to anymore. Payoffs are the cards tagged
+    /// #payoff, or the tops of the chains. Can be repeated
+    #[arg(long, value_name = "CARD")]
+    without: Vec<String>,
+    /// Print creatures to fill the gaps in the curve with, searched for on
+    /// Scryfall, instead of writing the graph. Suggestions stay within the
+    /// commanders' identity with --commander-identity, legal with
+    /// --legal-in, under --max-price and in the collection with --owned-only
+    #[arg(long, conflicts_with_all = ["check", "watch", "type_census"])]
+    suggest_fixes: bool,
+    /// Only suggest cards costing at most this many US dollars
+    #[arg(long, requires = "suggest_fixes")]
+    max_price: Option<f64>,
+    /// Print how many creatures of each type there are at each mana value,
+    /// flagging the types that can never chain with Pyre of Heroes, instead
+    /// of writing the graph
+    #[arg(long, conflicts_with_all = ["check", "watch"])]
+    type_census: bool,
+    /// Which tutoring engine to build the graph for
+    #[arg(short, long, value_enum, default_value_t)]
+    pod: Pod,
+    /// Build the graph for a well known tutor, by card name, instead of a
+    /// --pod: Birthing Pod, Neoform, Prime Speaker Vannifar, Pyre of Heroes,
+    /// Chord of Calling, Finale of Devastation, Green Sun's Zenith,
+    /// Ranger-Captain of Eos or Recruiter of the Guard
+    #[arg(long, conflicts_with = "pod")]
+    engine: Option<Engine>,
+    /// Only connect cards to the cards within their color identity, for
+    /// engines that can only find cards of the fetcher's colors
+    #[arg(long)]
+    within_identity: bool,
+    /// Which words of the type lines must be shared in pyre-of-heroes mode
+    #[arg(long, value_enum, default_value_t)]
+    match_on: MatchOn,
+    /// Label pyre-of-heroes edges with every type the cards share instead of
+    /// only the first
+    #[arg(long)]
+    all_shared_types: bool,
+    /// Output format. Anything other than dot requires graphviz to be installed
+    #[arg(short, long, value_enum, default_value_t)]
+    format: OutputFormat,
+    /// The graphviz layout engine to render with. sfdp copes best with big
+    /// graphs, like cubes
+    #[arg(long, value_enum, default_value_t)]
+    layout: Layout,
+    /// Where to write the output. Defaults to `graph.<format>`
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+    /// Open the output with the default viewer once it's rendered
+    #[arg(long, conflicts_with = "split_by_cmc")]
+    open: bool,
+    /// Write one output per pair of adjacent mana values, like
+    /// `graph-2-3.dot`, with only the cards of those two mana values
+    #[arg(long)]
+    split_by_cmc: bool,
+    /// Keep running and regenerate the output whenever the decklist changes
+    #[arg(short, long, requires = "file")]
+    watch: bool,
+    /// Only keep creatures with at least one of these types
+    #[arg(long, value_delimiter = ',')]
+    only_types: Vec<String>,
+    /// Drop creatures with a lower mana value than this
+    #[arg(long)]
+    min_cmc: Option<u8>,
+    /// Drop creatures with a higher mana value than this
+    #[arg(long)]
+    max_cmc: Option<u8>,
+    /// Only keep creatures meeting all of these: a type like `legendary`, a
+    /// color like `green`, or a comparison of power, toughness or mv like
+    /// `toughness<=2`. Prefix with ! to negate
+    #[arg(long, value_delimiter = ',')]
+    require: Vec<Requirement>,
+    /// Only resolve the decklist and report problems with it, without writing any output
+    #[arg(long, conflicts_with = "watch")]
+    check: bool,
+    /// Report cards that aren't legal in this format with --check, or only
+    /// suggest cards legal in it with --suggest-fixes
+    #[arg(long, value_parser = parse_format)]
+    legal_in: Option<Format>,
+    /// Skip cards that can't be resolved instead of failing, listing them at the end
+    #[arg(long)]
+    lenient: bool,
+    /// Treat names that only fuzzy match a card as unresolvable
+    #[arg(long)]
+    strict_names: bool,
+    /// How the decklist is formatted. Files ending in .dck are read as dck
+    /// unless another format is given
+    #[arg(long, value_enum, default_value_t)]
+    input_format: InputFormat,
+    /// Where card data comes from: `scryfall`, `cache` (only cache.json, no
+    /// network), `bulk:<path>` (a Scryfall bulk data file) or `mtgjson:<path>`
+    /// (an MTGJSON AtomicCards or AllPrintings file)
+    #[arg(long, default_value = "scryfall")]
+    source: SourceArg,
+    /// Save the resolved cards to this file, before --only-types and the mana
+    /// value filters are applied
+    #[arg(long)]
+    save_resolved: Option<PathBuf>,
+    /// Build the graph from cards saved with --save-resolved instead of
+    /// resolving a decklist
+    #[arg(long, conflicts_with_all = ["file", "watch", "check", "source"])]
+    load_resolved: Option<PathBuf>,
+    /// A Deckbox or Moxfield csv export of the cards you own. Cards missing
+    /// from it are drawn dashed
+    #[arg(long)]
+    collection: Option<PathBuf>,
+    /// Drop creatures that aren't in the --collection
+    #[arg(long, requires = "collection")]
+    owned_only: bool,
+    #[arg(skip)]
+    owned: Option<Collection>,
+    /// Drop creatures outside the combined color identity of the decklist's
+    /// commanders
+    #[arg(long)]
+    commander_identity: bool,
+    /// Build the graph from every creature matching this Scryfall search
+    /// instead of a decklist, e.g. 'id<=gw cmc<=6 f:edh'
+    #[arg(long, conflicts_with_all = ["file", "watch", "check", "load_resolved", "source"])]
+    query: Option<String>,
+    /// Stream newline delimited json events to stdout as cards are resolved
+    /// and the graph is built and written
+    #[arg(long, conflicts_with = "check")]
+    emit_events: bool,
+    /// Write every card down in this file as soon as it's resolved, so a run
+    /// that dies halfway resumes from it when run again with the same file.
+    /// Removed once the output is written
+    #[arg(long, conflicts_with_all = ["load_resolved", "query"])]
+    checkpoint: Option<PathBuf>,
+    /// Append how many orphans and how long a chain the graph has to the
+    /// decklist's history in this directory, for `trend`
+    #[arg(long, requires = "file")]
+    history_dir: Option<PathBuf>,
+    /// Write the output even if it was already rendered from the same cards
+    /// and options
+    #[arg(long)]
+    force: bool,
+    /// How many cards to look up at once
+    #[arg(long, default_value_t = decklist::DEFAULT_CONCURRENCY)]
+    concurrency: NonZeroUsize,
+    /// Send Scryfall requests here instead, e.g. to a caching mirror. Proxies
+    /// are taken from the HTTPS_PROXY, HTTP_PROXY and NO_PROXY environment
+    /// variables
+    #[arg(long, global = true, value_parser = parse_api_base)]
+    api_base: Option<String>,
+    /// Only print errors
+    #[arg(short, long, conflicts_with = "verbose")]
+    quiet: bool,
+    /// Print every card as it's added and every cache miss. Repeat for trace output
+    #[arg(short, long, action = ArgAction::Count)]
+    verbose: u8,
+    /// Format of the diagnostics printed to stderr
+    #[arg(long, value_enum, default_value_t)]
+    log_format: LogFormat,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-struct Card {
-    name: String,
-    cmc: u8,
-    types: Vec<String>,
+fn parse_api_base(s: &str) -> Result<String, String> {
+    match url::Url::parse(s) {
+        Ok(url) if url.cannot_be_a_base() => Err(format!("not a base url: {s}")),
+        Ok(_) => Ok(s.to_owned()),
+        Err(e) => Err(format!("{s}: {e}")),
+    }
+}
+
+fn parse_node_shape(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((ty, shape)) if !ty.is_empty() && !shape.is_empty() => {
+            Ok((ty.to_owned(), shape.to_owned()))
+        }
+        _ => Err(format!("expected TYPE=SHAPE, got {s}")),
+    }
+}
+
+fn parse_format(s: &str) -> Result<Format, String> {
+    serde_json::from_value(serde_json::Value::String(s.to_lowercase()))
+        .map_err(|_| format!("unknown format: {s}"))
+}
+
+#[derive(Debug, Clone)]
+enum SourceArg {
+    Scryfall,
+    Cache,
+    Bulk(PathBuf),
+    Mtgjson(PathBuf),
+}
+
+impl FromStr for SourceArg {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            Some(("bulk", path)) if !path.is_empty() => Ok(Self::Bulk(path.into())),
+            Some(("mtgjson", path)) if !path.is_empty() => Ok(Self::Mtgjson(path.into())),
+            None if s == "scryfall" => Ok(Self::Scryfall),
+            None if s == "cache" => Ok(Self::Cache),
+            _ => Err(format!(
+                "unknown source: {s}, expected scryfall, cache, bulk:<path> or mtgjson:<path>"
+            )),
+        }
+    }
+}
+
+impl SourceArg {
+    async fn open(&self) -> error::Result<Source> {
+        Ok(match self {
+            SourceArg::Scryfall => Arc::new(Scryfall),
+            SourceArg::Cache => Arc::new(CacheOnly),
+            SourceArg::Bulk(path) => {
+                debug!(path = %path.display(), "loading bulk data");
+                Arc::new(
+                    BulkData::load(path)
+                        .await
+                        .map_err(|e| load_error(path, e))?,
+                )
+            }
+            SourceArg::Mtgjson(path) => {
+                debug!(path = %path.display(), "loading mtgjson data");
+                Arc::new(Mtgjson::load(path).await.map_err(|e| load_error(path, e))?)
+            }
+        })
+    }
+}
+
+fn load_error(path: &std::path::Path, e: std::io::Error) -> Error {
+    Error::Input(std::io::Error::new(
+        e.kind(),
+        format!("{}: {e}", path.display()),
+    ))
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Print shell completions to stdout
+    Completions { shell: clap_complete::Shell },
+    /// Serve graphs over HTTP: POST a decklist, or GET with a moxfield ?url=,
+    /// to /graph?pod=..&format=..&highlight=..
+    Serve {
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1")]
+        host: std::net::IpAddr,
+        #[arg(short, long, default_value_t = 8080)]
+        port: u16,
+        /// Where card data comes from, like the top level --source
+        #[arg(long, default_value = "scryfall")]
+        source: SourceArg,
+    },
+    /// Judge how poddable a cube is: build graphs for random draft pools and
+    /// report how long their chains get and how many creatures are orphans
+    Cube {
+        /// The cube list
+        file: PathBuf,
+        /// How many cards are in each pool
+        #[arg(long, default_value_t = 40)]
+        sample_size: usize,
+        /// How many pools to draw
+        #[arg(long, default_value_t = 100)]
+        samples: usize,
+        /// Seed for drawing the pools, to repeat a run. Random by default
+        #[arg(long)]
+        seed: Option<u64>,
+        #[arg(short, long, value_enum, default_value_t)]
+        pod: Pod,
+        /// Where card data comes from, like the top level --source
+        #[arg(long, default_value = "scryfall")]
+        source: SourceArg,
+    },
+    /// Share the card cache, so others don't have to look every card up again
+    Cache {
+        #[command(subcommand)]
+        command: CacheCommand,
+    },
+    /// Shuffle a decklist into opening hands and report how often they have
+    /// an engine and a creature that climbs to each of the deck's payoffs
+    SimulateHands {
+        /// The decklist
+        file: PathBuf,
+        /// How many hands to shuffle
+        #[arg(long, default_value_t = 1000)]
+        hands: usize,
+        /// How many cards an opening hand has
+        #[arg(long, default_value_t = 7)]
+        hand_size: usize,
+        /// How many cards to draw after the opening hand
+        #[arg(long, default_value_t = 0)]
+        draws: usize,
+        /// Seed for shuffling, to repeat a run. Random by default
+        #[arg(long)]
+        seed: Option<u64>,
+        #[arg(short, long, value_enum, default_value_t)]
+        pod: Pod,
+        /// Where card data comes from, like the top level --source
+        #[arg(long, default_value = "scryfall")]
+        source: SourceArg,
+    },
+    /// Write a pdf with the graph of a decklist, its statistics, its longest
+    /// chains and suggestions for improving it
+    Report {
+        /// The decklist
+        file: PathBuf,
+        /// Where to write the pdf
+        #[arg(short, long, default_value = "report.pdf")]
+        output: PathBuf,
+        #[arg(short, long, value_enum, default_value_t)]
+        pod: Pod,
+        /// The graphviz layout engine to render the graph with
+        #[arg(long, value_enum, default_value_t)]
+        layout: Layout,
+        /// Where card data comes from, like the top level --source
+        #[arg(long, default_value = "scryfall")]
+        source: SourceArg,
+    },
+    /// Print how the orphans and the longest chain of a decklist changed
+    /// across the revisions recorded with --history-dir
+    Trend {
+        /// The decklist
+        file: PathBuf,
+        /// Where the history was recorded
+        #[arg(long)]
+        history_dir: PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+enum CacheCommand {
+    /// Write every cached card to a file
+    Export {
+        /// Where to write the cards
+        file: PathBuf,
+    },
+    /// Add the cards of a file written by `cache export` to the cache.
+    /// Cards already cached are kept, and cards filed under a name that
+    /// isn't theirs are left out
+    Import {
+        /// The exported cards
+        file: PathBuf,
+    },
+}
+
+#[allow(clippy::enum_variant_names)] // the names double as the --pod values
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+enum Pod {
+    #[default]
+    BirthingPod,
+    PyreOfHeroes,
+    Vannifar,
+    Transmute,
+    ProliferatePod,
+    XSpell,
+}
+
+/// Runs `$body` for the [PodKind] `$pod` picks, with its type named `$K` and,
+/// in the second form, a value of it named `$kind`: `$pyre` for Pyre of
+/// Heroes, which has options, and the default for the others.
+///
+/// ```ignore
+/// with_kind!(pod, |K| report::report::<K>(file).await)
+/// with_kind!(pod, pyre, |kind: K| run_for(kind, &args).await)
+/// ```
+macro_rules! with_kind {
+    (@as $ty:ty, $value:expr, $kind:ident, $K:ident, $body:expr) => {{
+        #[allow(dead_code)]
+        type $K = $ty;
+        let $kind: $K = $value;
+        $body
+    }};
+    ($pod:expr, |$K:ident| $body:expr) => {
+        $crate::with_kind!($pod, Default::default(), |_kind: $K| $body)
+    };
+    ($pod:expr, $pyre:expr, |$kind:ident: $K:ident| $body:expr) => {
+        match $pod {
+            $crate::Pod::BirthingPod => {
+                $crate::with_kind!(@as pyre::BirthingPod, pyre::BirthingPod, $kind, $K, $body)
+            }
+            $crate::Pod::PyreOfHeroes => {
+                $crate::with_kind!(@as pyre::PyreOfHeroes, $pyre, $kind, $K, $body)
+            }
+            $crate::Pod::Vannifar => {
+                $crate::with_kind!(@as pyre::Vannifar, pyre::Vannifar, $kind, $K, $body)
+            }
+            $crate::Pod::Transmute => {
+                $crate::with_kind!(@as pyre::Transmute, pyre::Transmute, $kind, $K, $body)
+            }
+            $crate::Pod::ProliferatePod => {
+                $crate::with_kind!(@as pyre::ProliferatePod, pyre::ProliferatePod, $kind, $K, $body)
+            }
+            $crate::Pod::XSpell => {
+                $crate::with_kind!(@as pyre::XSpell, pyre::XSpell, $kind, $K, $body)
+            }
+        }
+    };
+}
+pub(crate) use with_kind;
+
+impl Args {
+    fn verbosity(&self) -> Verbosity {
+        match (self.quiet, self.verbose) {
+            (true, _) => Verbosity::Quiet,
+            (false, 0) => Verbosity::Normal,
+            (false, 1) => Verbosity::Verbose,
+            (false, _) => Verbosity::Trace,
+        }
+    }
+
+    /// Whether the card passes the `--only-types`, `--min-cmc`, `--max-cmc`,
+    /// `--require`, `--owned-only` and `--commander-identity` filters.
+    fn keeps(&self, card: &Card, identity: Option<&[Color]>) -> bool {
+        (self.only_types.is_empty()
+            || card
+                .types
+                .iter()
+                .any(|t| self.only_types.iter().any(|o| o.eq_ignore_ascii_case(t))))
+            && self.min_cmc.is_none_or(|min| card.cmc >= min)
+            && self.max_cmc.is_none_or(|max| card.cmc <= max)
+            && self.require.iter().all(|r| r.matches(card))
+            && (!self.owned_only || self.owned.as_ref().is_none_or(|o| o.contains(card)))
+            && identity.is_none_or(|i| card.within_identity(i))
+    }
+
+    /// The combined color identity of the `commanders`, or `None` without
+    /// `--commander-identity` or without commanders.
+    fn identity(&self, commanders: &[Card]) -> Option<Vec<Color>> {
+        if !self.commander_identity {
+            return None;
+        }
+        if commanders.is_empty() {
+            warn!("--commander-identity was given but the decklist has no commander section");
+            return None;
+        }
+        let mut identity = Vec::new();
+        for color in commanders.iter().flat_map(|c| &c.color_identity) {
+            if !identity.contains(color) {
+                identity.push(*color);
+            }
+        }
+        Some(identity)
+    }
+
+    /// Drops the cards that don't pass [keeps](Self::keeps), with the
+    /// identity of the deck's `commanders`.
+    fn filter(&self, mut cards: Vec<Card>, commanders: &[Card]) -> Vec<Card> {
+        let identity = self.identity(commanders);
+        cards.retain(|c| {
+            let keep = self.keeps(c, identity.as_deref());
+            if !keep {
+                debug!(name = %c.name, "filtered out");
+            }
+            keep
+        });
+        cards
+    }
+
+    /// Fails with the unresolved lines, unless running in `--lenient` mode.
+    fn tolerate(&self, failed: Vec<FailedLine>) -> error::Result<Vec<FailedLine>> {
+        if failed.is_empty() || self.lenient {
+            Ok(failed)
+        } else {
+            Err(Error::Unresolved(failed))
+        }
+    }
+
+    /// The `--input-format`, or dck for `.dck` files when left on auto.
+    fn input_format(&self) -> InputFormat {
+        let is_dck = self
+            .file
+            .as_ref()
+            .is_some_and(|f| f.extension().is_some_and(|e| e.eq_ignore_ascii_case("dck")));
+        match self.input_format {
+            InputFormat::Auto if is_dck => InputFormat::Dck,
+            format => format,
+        }
+    }
+
+    /// Reads the `--collection`, if one was given.
+    async fn load_collection(&mut self) -> error::Result<()> {
+        if let Some(path) = &self.collection {
+            let csv = tokio::fs::read_to_string(path)
+                .await
+                .map_err(|e| load_error(path, e))?;
+            let owned = Collection::from_csv(&csv).map_err(|e| load_error(path, e))?;
+            debug!(cards = owned.len(), "loaded collection");
+            self.owned = Some(owned);
+        }
+        Ok(())
+    }
+
+    async fn source(&self) -> error::Result<Source> {
+        let source = self.source.open().await?;
+        match &self.checkpoint {
+            Some(path) => checkpoint::wrap(path, source).await,
+            None => Ok(source),
+        }
+    }
+
+    /// How `--cluster-by`, `--stats-footer`, `--node-shape` and the edge label
+    /// filters say to draw the graph.
+    fn render_options(&self) -> RenderOptions {
+        RenderOptions {
+            edge_filter: EdgeFilter {
+                hide: self.hide_edges_with_label.clone(),
+                only: self.only_edges_with_label.clone(),
+            },
+            cluster_by: self.cluster_by,
+            stats_footer: self.stats_footer,
+            node_shapes: NodeShapes(
+                self.node_shape
+                    .iter()
+                    .cloned()
+                    .chain(NodeShapes::default().0)
+                    .collect(),
+            ),
+        }
+    }
+
+    fn output(&self) -> PathBuf {
+        self.output
+            .clone()
+            .unwrap_or_else(|| PathBuf::from("graph").with_extension(self.format.extension()))
+    }
+}
+
+type Source = Arc<dyn CardSource>;
+
+type CardStream<'s> = Pin<Box<dyn Stream<Item = error::Result<Card>> + 's>>;
+
+/// A resolved decklist: the creatures the graph is built from, and what the
+/// rest of the deck tells about them.
+#[derive(Default)]
+struct Deck {
+    creatures: Vec<Card>,
+    failed: Vec<FailedLine>,
+    /// The commanders, creatures or not.
+    commanders: Vec<Card>,
+    /// The [engine cards](engine::cards_for) in the deck.
+    engines: Vec<String>,
+    /// How many cards weren't creatures.
+    non_creatures: usize,
+}
+
+impl Deck {
+    /// A deck of creatures that were already resolved, like from
+    /// `--load-resolved` or `--query`.
+    fn of_creatures(creatures: Vec<Card>) -> Self {
+        Self {
+            commanders: creatures.iter().filter(|c| c.commander).cloned().collect(),
+            creatures,
+            ..Self::default()
+        }
+    }
 }
 
 #[tokio::main]
-async fn main() -> scryfall::Result<()> {
-    let args = Args::parse();
-    let creatures = match args.file {
+async fn main() -> ExitCode {
+    let mut args = Args::parse();
+    if let Some(engine) = args.engine {
+        args.pod = engine.pod;
+        args.require.extend(engine.requirements());
+    }
+    if let Some(base) = &args.api_base {
+        pyre::source::set_api_base(base.as_str());
+    }
+    if let Some(Command::Completions { shell }) = args.command {
+        clap_complete::generate(
+            shell,
+            &mut Args::command(),
+            env!("CARGO_BIN_NAME"),
+            &mut std::io::stdout(),
+        );
+        return ExitCode::SUCCESS;
+    }
+    let progress = Progress::new(
+        args.quiet || args.log_format == LogFormat::Json,
+        args.emit_events,
+    );
+    logging::init(args.verbosity(), args.log_format, &progress);
+    if let Err(e) = args.load_collection().await {
+        error!("{e}");
+        return cli::exit_code(&e);
+    }
+    let result = match &args.command {
+        Some(Command::Serve { host, port, source }) => match source.open().await {
+            Ok(source) => serve::serve((*host, *port).into(), source).await,
+            Err(e) => Err(e),
+        },
+        Some(Command::Cache {
+            command: CacheCommand::Export { file },
+        }) => match pyre::source::export_cache(file).await {
+            Ok(count) => {
+                info!("exported {count} cards to {}", file.display());
+                Ok(())
+            }
+            Err(e) => Err(Error::Output(e)),
+        },
+        Some(Command::Cache {
+            command: CacheCommand::Import { file },
+        }) => match pyre::source::import_cache(file).await {
+            Ok(imported) => {
+                info!(
+                    "imported {} cards, {} were already cached and {} were filed under another name",
+                    imported.added, imported.already_cached, imported.rejected
+                );
+                Ok(())
+            }
+            Err(e) => Err(load_error(file, e)),
+        },
+        Some(Command::Cube {
+            file,
+            sample_size,
+            samples,
+            seed,
+            pod,
+            source,
+        }) => match source.open().await {
+            Ok(s) => with_kind!(pod, |K| {
+                cube::cube::<K>(file, *sample_size, *samples, *seed, &s, &progress).await
+            }),
+            Err(e) => Err(e),
+        },
+        Some(Command::SimulateHands {
+            file,
+            hands,
+            hand_size,
+            draws,
+            seed,
+            pod,
+            source,
+        }) => {
+            let deal = || hands::Deal {
+                hands: *hands,
+                hand_size: *hand_size,
+                draws: *draws,
+            };
+            match source.open().await {
+                Ok(s) => with_kind!(pod, |K| {
+                    hands::simulate::<K>(*pod, file, deal(), *seed, &s, &progress).await
+                }),
+                Err(e) => Err(e),
+            }
+        }
+        Some(Command::Report {
+            file,
+            output,
+            pod,
+            layout,
+            source,
+        }) => match source.open().await {
+            Ok(s) => with_kind!(pod, |K| {
+                report::report::<K>(file, output, *layout, &s, &progress).await
+            }),
+            Err(e) => Err(e),
+        },
+        Some(Command::Trend { file, history_dir }) => history::trend(history_dir, file).await,
+        _ => match args.source().await {
+            Ok(source) if args.check => match check::check(&args, &source, &progress).await {
+                Ok(true) => Ok(()),
+                Ok(false) => return ExitCode::FAILURE,
+                Err(e) => Err(e),
+            },
+            Ok(source) => {
+                let pyre = PyreOfHeroes {
+                    match_on: args.match_on,
+                    all_shared_types: args.all_shared_types,
+                };
+                with_kind!(args.pod, pyre, |kind: K| {
+                    run_for(kind, &args, &source, &progress).await
+                })
+            }
+            Err(e) => Err(e),
+        },
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            error!("{e}");
+            cli::exit_code(&e)
+        }
+    }
+}
+
+async fn open_reader(args: &Args) -> error::Result<Pin<Box<dyn AsyncRead + Send>>> {
+    #[cfg(feature = "clipboard")]
+    if args.from_clipboard {
+        let decklist = cli::clipboard::paste().await.map_err(Error::Input)?;
+        return Ok(Box::pin(std::io::Cursor::new(decklist)));
+    }
+    Ok(match &args.file {
         Some(path) if path.as_os_str() != "-" => {
-            decklist::parse(File::open(path).await?).await.boxed()
-                as Pin<Box<dyn Stream<Item = scryfall::Result<Card>>>>
-        }
-        _ => decklist::parse(stdin()).await.boxed(),
-    };
-    let graph = creatures
-        .try_fold(
-            pyre_graph::PodGraph::<pyre_graph::BirthingPod>::new(),
-            |mut g, c| async move {
-                eprintln!("added {}", c.name);
-                g.add_card(c);
-                Ok(g)
+            Box::pin(File::open(path).await.map_err(Error::Input)?)
+        }
+        _ => {
+            if std::io::stdin().is_terminal() && !args.quiet {
+                eprintln!("{}", args.input_format().prompt());
+            }
+            Box::pin(stdin())
+        }
+    })
+}
+
+/// Every card of the decklist, creatures or not.
+async fn open_input<'a>(
+    args: &'a Args,
+    source: &Source,
+    progress: &Progress,
+) -> error::Result<CardStream<'a>> {
+    let cards = decklist::parse(
+        open_reader(args).await?,
+        source.clone(),
+        args.input_format(),
+        args.concurrency,
+        progress.clone(),
+        args.strict_names,
+        Some,
+    )
+    .await;
+    Ok(
+        match args.file.as_deref().filter(|f| f.as_os_str() != "-") {
+            Some(file) => decklist::in_file(cards, &file.to_string_lossy()).boxed(),
+            None => cards.boxed(),
+        },
+    )
+}
+
+/// Resolves the decklist, setting the creatures apart from the rest.
+async fn read_deck(args: &Args, source: &Source, progress: &Progress) -> error::Result<Deck> {
+    let (cards, failed) =
+        collect_cards(open_input(args, source, progress).await?, progress).await?;
+    let engines = engine::cards_for(args.pod, args.engine);
+    let mut deck = Deck {
+        failed,
+        ..Deck::default()
+    };
+    for card in cards {
+        if card.commander {
+            deck.commanders.push(card.clone());
+        }
+        if engine::is_engine(&engines, &card.name) {
+            deck.engines.push(card.name.clone());
+        }
+        match decklist::into_creature(card) {
+            Some(creature) => deck.creatures.push(creature),
+            None => deck.non_creatures += 1,
+        }
+    }
+    Ok(deck)
+}
+
+/// Drains the card stream, resolving every line before reporting the ones
+/// that failed.
+async fn collect_cards(
+    creatures: CardStream<'_>,
+    progress: &Progress,
+) -> error::Result<(Vec<Card>, Vec<FailedLine>)> {
+    let resolved = decklist::collect(creatures).await;
+    progress.finish();
+    resolved
+}
+
+/// The creatures matching a Scryfall search.
+async fn search(query: &str) -> error::Result<Vec<Card>> {
+    let cards = pyre::source::search(query)
+        .try_filter_map(|card| future::ready(Ok(decklist::into_creature(card))))
+        .try_collect::<Vec<_>>()
+        .await;
+    cards.map_err(|e| {
+        Error::Line(FailedLine {
+            line: Line {
+                text: query.to_owned(),
+                name: query.to_owned(),
+                commander: false,
+                tags: Vec::new(),
+                number: 0,
+                file: None,
             },
-        )
-        .await?;
-    graph.to_img("graph.dot", args.highlight.as_deref()).await?;
+            error: e.into(),
+        })
+    })
+}
+
+fn report_skipped(failed: Vec<FailedLine>) {
+    if !failed.is_empty() {
+        warn!("skipped cards: {}", Error::Unresolved(failed));
+    }
+}
+
+/// The graph of `cards`, read back from the [graph cache](graph_cache) when
+/// the same cards were graphed with the same engine before.
+async fn build_graph<K: PodKind + Clone + Default + Debug>(
+    kind: K,
+    cards: Vec<Card>,
+    args: &Args,
+) -> PodGraph<K> {
+    let key = graph_cache::key(&kind, &cards);
+    let mut graph = match graph_cache::load(key, kind.clone()).await {
+        Some(graph) => graph,
+        None => {
+            let graph = PodGraphBuilder::new()
+                .with_kind(kind)
+                .add_cards(cards)
+                .build();
+            graph_cache::store(key, &graph).await;
+            graph
+        }
+    };
+    graph.set_highlight(args.highlight.clone());
+    graph.set_owned(args.owned.clone());
+    let name = match (&args.file, &args.query) {
+        (Some(file), _) if file.as_os_str() != "-" => {
+            file.file_stem().map(|s| s.to_string_lossy().into_owned())
+        }
+        (_, Some(query)) => Some(query.clone()),
+        _ => None,
+    };
+    graph.set_metadata(Some(Metadata {
+        name,
+        date: Some(chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true)),
+    }));
+    graph
+}
+
+/// Removes every card outside the component of the `--component-of` card.
+fn restrict<K: PodKind>(graph: &mut PodGraph<K>, args: &Args) -> error::Result<()> {
+    let Some(name) = &args.component_of else {
+        return Ok(());
+    };
+    let keep = graph
+        .component_of(name)
+        .ok_or_else(|| Error::Input(std::io::Error::other(format!("{name} is not in the graph"))))?
+        .into_iter()
+        .map(|c| c.name.clone())
+        .collect::<HashSet<_>>();
+    let others = graph
+        .cards()
+        .filter(|c| !keep.contains(&c.name))
+        .map(|c| c.name.clone())
+        .collect::<Vec<_>>();
+    for name in others {
+        graph.remove_card(&name);
+    }
+    Ok(())
+}
+
+/// Warns when `--highlight` matches more than one card, listing them, or
+/// when it matches none, listing the cards with similar names and dropping
+/// the highlight. With `--highlight-exact` only a card with exactly that name
+/// matches, and with `--strict` matching none is an error.
+fn check_highlight<K: PodKind>(graph: &mut PodGraph<K>, args: &Args) -> error::Result<()> {
+    let Some(name) = graph.highlight().map(str::to_owned) else {
+        return Ok(());
+    };
+    let matches = graph
+        .highlight_matches()
+        .into_iter()
+        .map(|c| c.name.as_str())
+        .collect::<Vec<_>>();
+    if matches.is_empty() || args.highlight_exact && matches != [name.as_str()] {
+        let message = match args.highlight_exact {
+            true => format!("no card is named {name:?}"),
+            false => format!("no card matches {name:?}"),
+        };
+        let similar = similar_names(graph, &name);
+        let hint = match similar.is_empty() {
+            true => String::new(),
+            false => format!(", did you mean {}?", similar.join(", ")),
+        };
+        if args.strict {
+            return Err(Error::Input(std::io::Error::other(format!(
+                "{message}{hint}"
+            ))));
+        }
+        warn!("{message}, nothing is highlighted{hint}");
+        graph.set_highlight(None);
+    } else if let [first, _, ..] = matches[..] {
+        warn!(
+            "{name:?} matches {}, highlighting {first}",
+            matches.join(", ")
+        );
+    }
+    Ok(())
+}
+
+/// Up to five names of cards in the graph that look like `name`, most
+/// similar first.
+fn similar_names<K: PodKind>(graph: &PodGraph<K>, name: &str) -> Vec<String> {
+    let name = name.to_lowercase();
+    let mut similar = graph
+        .cards()
+        .map(|c| (strsim::jaro_winkler(&name, &c.name.to_lowercase()), &c.name))
+        .filter(|(score, _)| *score > 0.7)
+        .collect::<Vec<_>>();
+    similar.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+    similar
+        .into_iter()
+        .take(5)
+        .map(|(_, name)| name.clone())
+        .collect()
+}
+
+/// Removes the `--without` cards, warning about the payoffs that could be
+/// climbed to before and can't anymore.
+fn remove_without<K: PodKind>(graph: &mut PodGraph<K>, args: &Args) {
+    if args.without.is_empty() {
+        return;
+    }
+    let climbed = |graph: &PodGraph<K>, name: &str| {
+        graph
+            .cards_that_can_reach(name)
+            .is_some_and(|cards| cards.len() > 1)
+    };
+    let payoffs = report::payoffs(graph)
+        .into_iter()
+        .filter(|p| climbed(graph, &p.name))
+        .map(|p| p.name.clone())
+        .collect::<Vec<_>>();
+    for name in &args.without {
+        if graph.remove_card(name).is_none() {
+            warn!("--without {name:?} isn't in the graph");
+        }
+    }
+    let lost = payoffs
+        .into_iter()
+        .filter(|p| !climbed(graph, p))
+        .collect::<Vec<_>>();
+    match lost.is_empty() {
+        true => info!("every payoff can still be climbed to"),
+        false => warn!("nothing climbs to these anymore: {}", lost.join(", ")),
+    }
+}
+
+/// Writes the graph in the requested format, emitting its events first with
+/// `--emit-events`, or one output per pair of adjacent mana values with
+/// `--split-by-cmc`. Returns whether anything was written.
+async fn render<K: PodKind + Clone>(graph: &PodGraph<K>, args: &Args) -> error::Result<bool> {
+    if args.emit_events {
+        events::graph(graph);
+    }
+    let output = args.output();
+    if !args.split_by_cmc {
+        return render_to(graph, args, &output).await;
+    }
+    let cmcs = graph.cards().map(|c| c.cmc).collect::<BTreeSet<_>>();
+    let mut wrote = false;
+    for &low in cmcs.iter().filter(|c| cmcs.contains(&(**c + 1))) {
+        let mut tier = graph.clone();
+        let others = graph
+            .cards()
+            .filter(|c| c.cmc != low && c.cmc != low + 1)
+            .map(|c| c.name.clone())
+            .collect::<Vec<_>>();
+        for name in others {
+            tier.remove_card(&name);
+        }
+        let mut name = output.file_stem().unwrap_or_default().to_owned();
+        name.push(format!("-{low}-{}", low + 1));
+        let tier_output = output
+            .with_file_name(name)
+            .with_extension(output.extension().unwrap_or_default());
+        wrote |= render_to(&tier, args, &tier_output).await?;
+    }
+    Ok(wrote)
+}
+
+/// Writes the graph to `output`. Skips writing if it was already rendered
+/// from the same graph and format, unless `--force`d. Returns whether it
+/// wrote.
+async fn render_to<K: PodKind + Clone>(
+    graph: &PodGraph<K>,
+    args: &Args,
+    output: &std::path::Path,
+) -> error::Result<bool> {
+    let key = stamp::key(graph, &args.render_options(), args.format, args.layout);
+    if !args.force && stamp::is_fresh(output, key).await {
+        info!("{} unchanged", output.display());
+        if args.emit_events {
+            events::emit(&events::Event::Unchanged { path: output });
+        }
+        return Ok(false);
+    }
+    write_output(graph, args, output).await?;
+    stamp::write(output, key).await;
+    if args.emit_events {
+        events::emit(&events::Event::Written { path: output });
+    }
+    Ok(true)
+}
+
+async fn write_output<K: PodKind + Clone>(
+    graph: &PodGraph<K>,
+    args: &Args,
+    output: &std::path::Path,
+) -> error::Result<()> {
+    match args.format {
+        OutputFormat::Json => {
+            let json = serde_json::to_vec_pretty(graph).expect("graphs always serialize");
+            return tokio::fs::write(output, json).await.map_err(Error::Output);
+        }
+        OutputFormat::Decklist => {
+            return tokio::fs::write(output, graph.to_decklist())
+                .await
+                .map_err(Error::Output);
+        }
+        OutputFormat::Proxies => {
+            let sheet = proxies::sheet(&proxies::chain(graph));
+            return tokio::fs::write(output, sheet).await.map_err(Error::Output);
+        }
+        OutputFormat::Markdown => {
+            return tokio::fs::write(output, markdown::report(graph, &args.render_options()))
+                .await
+                .map_err(Error::Output);
+        }
+        OutputFormat::Walkthrough => {
+            return write_walkthrough(graph, &args.render_options(), args.layout, output).await
+        }
+        _ => {}
+    }
+    let source = output.with_extension(OutputFormat::Dot.extension());
+    graph
+        .to_img(&source, &args.render_options())
+        .await
+        .map_err(Error::Output)?;
+    graphviz::render(&source, args.format, args.layout, output)
+        .await
+        .map_err(Error::Output)
+}
+
+/// Writes a frame per step of the [chain](proxies::chain) into the `output`
+/// directory, as `01.dot` rendered into `01.svg` and so on.
+async fn write_walkthrough<K: PodKind>(
+    graph: &PodGraph<K>,
+    options: &RenderOptions,
+    layout: Layout,
+    output: &std::path::Path,
+) -> error::Result<()> {
+    let chain = proxies::chain(graph);
+    let names = chain.iter().map(|c| c.name.as_str()).collect::<Vec<_>>();
+    let frames = graph.to_walkthrough(&names, options).await;
+    tokio::fs::create_dir_all(output)
+        .await
+        .map_err(Error::Output)?;
+    let width = frames.len().to_string().len().max(2);
+    for (i, frame) in frames.into_iter().enumerate() {
+        let source = output.join(format!("{:0width$}.dot", i + 1));
+        tokio::fs::write(&source, frame)
+            .await
+            .map_err(Error::Output)?;
+        let svg = source.with_extension(OutputFormat::Svg.extension());
+        graphviz::render(&source, OutputFormat::Svg, layout, &svg)
+            .await
+            .map_err(Error::Output)?;
+    }
+    Ok(())
+}
+
+/// [run]s with `kind`, restricted to cards [within the fetcher's
+/// identity](WithinIdentity) with `--within-identity`.
+async fn run_for<K: PodKind + Clone + Default + Debug>(
+    kind: K,
+    args: &Args,
+    source: &Source,
+    progress: &Progress,
+) -> error::Result<()> {
+    if args.within_identity {
+        run(WithinIdentity(kind), args, source, progress).await
+    } else {
+        run(kind, args, source, progress).await
+    }
+}
+
+async fn run<K: PodKind + Clone + Default + Debug>(
+    kind: K,
+    args: &Args,
+    source: &Source,
+    progress: &Progress,
+) -> error::Result<()> {
+    let deck = match (&args.load_resolved, &args.query) {
+        (Some(path), _) => Deck::of_creatures(resolved::load(path).await?),
+        (None, Some(query)) => Deck::of_creatures(search(query).await?),
+        (None, None) => {
+            let deck = read_deck(args, source, progress).await?;
+            let engines = engine::cards_for(args.pod, args.engine);
+            engine::warn_if_missing(args.pod, &engines, &deck.engines);
+            deck
+        }
+    };
+    let failed = args.tolerate(deck.failed)?;
+    if let Some(path) = &args.save_resolved {
+        resolved::save(path, &deck.creatures).await?;
+    }
+    let creatures = args.filter(deck.creatures, &deck.commanders);
+    let mut graph = build_graph(kind, creatures, args).await;
+    if graph.cards().next().is_none() {
+        return Err(Error::NoCreatures {
+            non_creatures: deck.non_creatures,
+        });
+    }
+    for card in graph.stranded() {
+        warn!(
+            name = %card.name,
+            cmc = card.cmc,
+            "no other creature is close enough in mana value to fetch it or be fetched by it"
+        );
+    }
+    check_highlight(&mut graph, args)?;
+    restrict(&mut graph, args)?;
+    remove_without(&mut graph, args);
+    bookmarks::apply(&mut graph, args).await?;
+    if args.type_census {
+        census::print(&graph);
+        return Ok(());
+    }
+    if args.suggest_fixes {
+        fixes::suggest(&graph, args.identity(&deck.commanders).as_deref(), args).await;
+        return Ok(());
+    }
+    render(&graph, args).await?;
+    history::record(&graph, args).await;
+    if let Some(path) = args.checkpoint.as_deref().filter(|_| !args.watch) {
+        checkpoint::finish(path).await;
+    }
+    report_skipped(failed);
+    #[cfg(feature = "clipboard")]
+    if args.to_clipboard {
+        cli::clipboard::copy_file(&args.output())
+            .await
+            .map_err(Error::Output)?;
+    }
+    if args.open {
+        if let Err(e) = graphviz::open(&args.output()) {
+            warn!("failed to open a viewer: {e}");
+        }
+    }
+    if args.watch {
+        watch::watch(graph, args, source, progress).await?;
+    }
     Ok(())
 }
@@ -0,0 +1,78 @@
+//! Importing decklists from Moxfield.
+
+use std::{collections::BTreeMap, io};
+
+use serde::Deserialize;
+use url::Url;
+
+use crate::{
+    error::{self, Error},
+    source,
+};
+
+const API: &str = "https://api2.moxfield.com/v2/decks/all";
+
+#[derive(Deserialize)]
+struct Entry {
+    quantity: u32,
+}
+
+#[derive(Deserialize)]
+struct Deck {
+    #[serde(default)]
+    commanders: BTreeMap<String, Entry>,
+    #[serde(default)]
+    companions: BTreeMap<String, Entry>,
+    #[serde(default)]
+    mainboard: BTreeMap<String, Entry>,
+}
+
+/// The public id of the deck at a `moxfield.com/decks/<id>` url.
+pub fn deck_id(url: &str) -> Option<String> {
+    let url = Url::parse(url).ok()?;
+    let host = url.host_str()?;
+    if host != "moxfield.com" && host != "www.moxfield.com" {
+        return None;
+    }
+    match url.path_segments()?.collect::<Vec<_>>()[..] {
+        ["decks", id, ..] if !id.is_empty() => Some(id.to_owned()),
+        _ => None,
+    }
+}
+
+/// Downloads the deck at a Moxfield url as a decklist, one `<count> <name>`
+/// line per card of its commander, companion and main boards, each under a
+/// section header. Fails with [Error::Input] if the url isn't a Moxfield deck,
+/// with an [InvalidInput](io::ErrorKind::InvalidInput) error, or if it can't
+/// be fetched.
+pub async fn fetch(url: &str) -> error::Result<String> {
+    let id = deck_id(url).ok_or_else(|| {
+        Error::Input(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("not a moxfield deck url: {url}"),
+        ))
+    })?;
+    let deck = source::client()
+        .get(format!("{API}/{id}"))
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| Error::Input(io::Error::other(e)))?
+        .json::<Deck>()
+        .await
+        .map_err(|e| Error::Input(io::Error::other(e)))?;
+    let sections = [
+        ("Commander", deck.commanders),
+        ("Companion", deck.companions),
+        ("Deck", deck.mainboard),
+    ];
+    let mut decklist = String::new();
+    for (header, cards) in sections.iter().filter(|(_, cards)| !cards.is_empty()) {
+        decklist.push_str(header);
+        decklist.push('\n');
+        for (name, entry) in cards {
+            decklist.push_str(&format!("{} {name}\n", entry.quantity));
+        }
+    }
+    Ok(decklist)
+}
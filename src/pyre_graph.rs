@@ -1,32 +1,112 @@
-use std::{collections::HashMap, fmt::Display, hash::Hash, io, marker::PhantomData, path::Path};
+//! The graph of which creatures can fetch which, for a given tutoring engine.
 
-use petgraph::{algo::DfsSpace, prelude::NodeIndex, Graph};
-use tokio::{
-    fs::File,
-    io::{AsyncWriteExt, BufWriter},
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt::{Display, Write as _},
+    hash::Hash,
+    io,
+    sync::{Arc, Mutex},
+    thread::available_parallelism,
 };
 
-use crate::Card;
+use petgraph::{
+    prelude::NodeIndex,
+    stable_graph::StableGraph,
+    visit::{EdgeRef, IntoEdgeReferences, NodeIndexable},
+    Direction,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use tracing::{debug, debug_span, trace, warn};
+
+use crate::{hash, intern::intern, Card, Collection, PodGraphBuilder};
+
+/// Below this many new cards per thread, [PodGraph::add_cards] isn't worth
+/// spreading over more threads.
+const CARDS_PER_THREAD: usize = 64;
 
-pub(super) struct Link<Edge> {
-    edge: Edge,
-    dir: LinkDirection,
+mod analysis;
+mod serde_impl;
+
+/// An edge between a card being added to the graph and one already in it.
+pub struct Link<Edge> {
+    /// What labels the edge.
+    pub edge: Edge,
+    /// Which way the edge points.
+    pub dir: LinkDirection,
 }
 
-enum LinkDirection {
+/// Which way a [Link] points, relative to the card being added.
+pub enum LinkDirection {
+    /// The existing card can fetch the new one.
     From,
+    /// The new card can fetch the existing one.
     To,
+    /// Each card can fetch the other.
+    Both,
 }
 
-pub(super) trait PodKind {
-    type Edge: Display + Hash + Eq;
-    fn check(new: &Card, existing: &Card) -> Option<Link<Self::Edge>>;
+/// A tutoring engine, defining which pairs of cards are connected. Engines
+/// are shared between threads while a graph is [built](PodGraph::add_cards).
+pub trait PodKind: Sync {
+    /// Identifies the engine in serialized graphs.
+    const NAME: &'static str;
+
+    /// What labels the edges.
+    type Edge: PodEdge + Clone + Send + Sync;
+
+    /// How `new` relates to `existing`, if at all.
+    fn check(&self, new: &Card, existing: &Card) -> Option<Link<Self::Edge>>;
+
+    /// The largest difference in mana value between two cards this engine
+    /// can connect, so that only cards that close are [checked](Self::check).
+    /// `None`, the default, checks every pair.
+    fn cmc_reach(&self) -> Option<u8> {
+        None
+    }
+
+    /// Whether the card lets the engine be activated again in the same turn,
+    /// for [PodGraph::activations_per_turn]. Defaults to no card.
+    fn untaps(&self, card: &Card) -> bool {
+        let _ = card;
+        false
+    }
+
+    /// Whether the engine fetches any card up to a mana value, like X spells,
+    /// rather than from one card to another. Such graphs are drawn as
+    /// nested bands of [reach](PodGraph::reachable_for) instead of edges.
+    /// Defaults to false.
+    fn reaches_up_to(&self) -> bool {
+        false
+    }
+}
+
+/// The data attached to the edges of a [PodKind], serialized along with the
+/// graph and rendered through these hooks.
+pub trait PodEdge: Serialize + DeserializeOwned {
+    /// The text drawn next to the edge.
+    fn label(&self) -> String;
+
+    /// Edges with the same color key are drawn with the same color. Defaults
+    /// to the [label](Self::label).
+    fn color_key(&self) -> String {
+        self.label()
+    }
+
+    /// The graphviz style of the edge, such as `dashed`. Defaults to none.
+    fn style(&self) -> Option<&'static str> {
+        None
+    }
 }
 
+/// Birthing Pod: sacrifice a creature to fetch one with a mana value one
+/// higher.
+#[derive(Debug, Default, Clone, Copy)]
 pub struct BirthingPod;
 
-#[derive(Debug, Hash, PartialEq, Eq, Copy, Clone, PartialOrd, Ord)]
-pub(crate) struct NoInfo;
+/// The empty edge label, for engines whose edges carry no information.
+#[derive(Debug, Hash, PartialEq, Eq, Copy, Clone, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct NoInfo;
 
 impl Display for NoInfo {
     fn fmt(&self, _: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -34,9 +114,37 @@ impl Display for NoInfo {
     }
 }
 
+impl PodEdge for NoInfo {
+    fn label(&self) -> String {
+        String::new()
+    }
+}
+
+/// Labeled with the string itself.
+impl PodEdge for String {
+    fn label(&self) -> String {
+        self.clone()
+    }
+}
+
+/// Labeled with the string itself.
+impl PodEdge for Arc<str> {
+    fn label(&self) -> String {
+        self.to_string()
+    }
+}
+
+/// Labeled with the number itself.
+impl PodEdge for u8 {
+    fn label(&self) -> String {
+        self.to_string()
+    }
+}
+
 impl PodKind for BirthingPod {
+    const NAME: &'static str = "birthing-pod";
     type Edge = NoInfo;
-    fn check(new: &Card, existing: &Card) -> Option<Link<Self::Edge>> {
+    fn check(&self, new: &Card, existing: &Card) -> Option<Link<Self::Edge>> {
         match (new.cmc as i16) - (existing.cmc as i16) {
             -1 => Some(Link {
                 edge: NoInfo,
@@ -49,136 +157,1361 @@ impl PodKind for BirthingPod {
             _ => None,
         }
     }
+
+    fn cmc_reach(&self) -> Option<u8> {
+        Some(1)
+    }
 }
 
-pub struct PyreOfHeroes;
+/// Pyre of Heroes: like [BirthingPod], but the creatures must share a
+/// creature type. Edges are labeled with the first shared type. Cards with
+/// [every creature type](Card::is_every_creature_type) share all of the
+/// other card's subtypes, and two of them share "Changeling".
+#[derive(Debug, Default, Clone, Copy)]
+pub struct PyreOfHeroes {
+    /// Which words of the type lines count as a shared type.
+    pub match_on: MatchOn,
+    /// Label edges with every shared type, like "Elf, Warrior", instead of
+    /// only the first.
+    pub all_shared_types: bool,
+}
+
+/// Which words of two cards' type lines [PyreOfHeroes] compares.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum MatchOn {
+    /// Only [subtypes](Card::subtypes), like Pyre itself.
+    #[default]
+    Subtype,
+    /// Any word, so even "Creature" or "Legendary" connect two cards.
+    AnyType,
+}
 
 impl PodKind for PyreOfHeroes {
-    type Edge = String;
-    fn check(new: &Card, existing: &Card) -> Option<Link<Self::Edge>> {
-        if let Some(ty) = new.types.iter().find(|t| existing.types.contains(t)) {
-            BirthingPod::check(new, existing).map(|t| Link {
-                edge: ty.clone(),
-                dir: t.dir,
-            })
+    const NAME: &'static str = "pyre-of-heroes";
+    type Edge = Arc<str>;
+    fn check(&self, new: &Card, existing: &Card) -> Option<Link<Self::Edge>> {
+        let link = BirthingPod.check(new, existing)?;
+        let shared: Vec<&Arc<str>> = match (
+            new.is_every_creature_type(),
+            existing.is_every_creature_type(),
+        ) {
+            (true, true) => {
+                return Some(Link {
+                    edge: intern("Changeling"),
+                    dir: link.dir,
+                })
+            }
+            (true, false) => existing.subtypes().collect(),
+            (false, true) => new.subtypes().collect(),
+            (false, false) => match self.match_on {
+                MatchOn::Subtype => new
+                    .subtypes()
+                    .filter(|t| existing.subtypes().any(|e| e == *t))
+                    .collect(),
+                MatchOn::AnyType => new
+                    .types
+                    .iter()
+                    .filter(|t| existing.types.contains(t))
+                    .collect(),
+            },
+        };
+        let edge = if self.all_shared_types {
+            let mut names = Vec::<&str>::new();
+            for ty in shared {
+                if !names.contains(&&**ty) {
+                    names.push(ty);
+                }
+            }
+            (!names.is_empty()).then(|| intern(&names.join(", ")))?
         } else {
-            None
+            (*shared.first()?).clone()
+        };
+        Some(Link {
+            edge,
+            dir: link.dir,
+        })
+    }
+
+    fn cmc_reach(&self) -> Option<u8> {
+        BirthingPod.cmc_reach()
+    }
+}
+
+/// Prime Speaker Vannifar: like [BirthingPod], but Vannifar is a creature,
+/// so creatures that untap a creature or permanent let it fetch again in the
+/// same turn.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Vannifar;
+
+impl PodKind for Vannifar {
+    const NAME: &'static str = "vannifar";
+    type Edge = NoInfo;
+    fn check(&self, new: &Card, existing: &Card) -> Option<Link<Self::Edge>> {
+        BirthingPod.check(new, existing)
+    }
+
+    fn cmc_reach(&self) -> Option<u8> {
+        BirthingPod.cmc_reach()
+    }
+
+    /// Looks for a sentence of the rules text that untaps a target, all or
+    /// each creature or permanent, like Kiora's Follower's "Untap another
+    /// target permanent."
+    fn untaps(&self, card: &Card) -> bool {
+        const TARGETS: [&str; 6] = [
+            "target ",
+            "another target ",
+            "up to one target ",
+            "up to two target ",
+            "all ",
+            "each ",
+        ];
+        let text = card.oracle_text.to_lowercase();
+        text.split(['.', '\n']).any(|sentence| {
+            sentence.match_indices("untap ").any(|(i, _)| {
+                let rest = &sentence[i + "untap ".len()..];
+                TARGETS.iter().any(|t| rest.starts_with(t))
+                    && (rest.contains("creature") || rest.contains("permanent"))
+            })
+        })
+    }
+}
+
+/// Birthing Pod with counter support, like Hardened Scales: proliferating
+/// or adding counters to Pod skips a rung. [Rung::Skip] edges connect cards
+/// two mana values apart, on top of the [BirthingPod] edges.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ProliferatePod;
+
+/// How far a [ProliferatePod] edge climbs.
+#[derive(Debug, Hash, PartialEq, Eq, Copy, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Rung {
+    /// One mana value up, like plain [BirthingPod].
+    Next,
+    /// Two mana values up, which takes an extra counter on Pod. Drawn dashed.
+    Skip,
+}
+
+impl PodEdge for Rung {
+    fn label(&self) -> String {
+        match self {
+            Rung::Next => String::new(),
+            Rung::Skip => "needs proliferate/counter tech".into(),
         }
     }
+
+    fn style(&self) -> Option<&'static str> {
+        match self {
+            Rung::Next => None,
+            Rung::Skip => Some("dashed"),
+        }
+    }
+}
+
+impl PodKind for ProliferatePod {
+    const NAME: &'static str = "proliferate-pod";
+    type Edge = Rung;
+    fn check(&self, new: &Card, existing: &Card) -> Option<Link<Self::Edge>> {
+        let (edge, dir) = match (new.cmc as i16) - (existing.cmc as i16) {
+            -1 => (Rung::Next, LinkDirection::To),
+            1 => (Rung::Next, LinkDirection::From),
+            -2 => (Rung::Skip, LinkDirection::To),
+            2 => (Rung::Skip, LinkDirection::From),
+            _ => return None,
+        };
+        Some(Link { edge, dir })
+    }
+
+    fn cmc_reach(&self) -> Option<u8> {
+        Some(2)
+    }
+}
+
+/// Transmute: discard a card to fetch one with the same mana value. Each
+/// card connects both ways to every other card of its mana value, so the
+/// graph is full of cycles. Edges are labeled with the shared mana value.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Transmute;
+
+impl PodKind for Transmute {
+    const NAME: &'static str = "transmute";
+    type Edge = u8;
+    fn check(&self, new: &Card, existing: &Card) -> Option<Link<Self::Edge>> {
+        (new.cmc == existing.cmc).then_some(Link {
+            edge: new.cmc,
+            dir: LinkDirection::Both,
+        })
+    }
+
+    fn cmc_reach(&self) -> Option<u8> {
+        Some(0)
+    }
 }
 
+/// X spells like Chord of Calling: fetch any creature with a mana value of
+/// at most X, so no card fetches another. The graph only groups the cards by
+/// mana value, the X it takes to fetch them.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct XSpell;
+
+impl PodKind for XSpell {
+    const NAME: &'static str = "x-spell";
+    type Edge = NoInfo;
+    fn check(&self, _: &Card, _: &Card) -> Option<Link<Self::Edge>> {
+        None
+    }
+
+    fn cmc_reach(&self) -> Option<u8> {
+        Some(0)
+    }
+
+    fn reaches_up_to(&self) -> bool {
+        true
+    }
+}
+
+/// Any engine, restricted to fetching cards within the color identity of the
+/// card that fetches them, for tutors that only find cards sharing the
+/// fetcher's colors. Serialized under the wrapped engine's name.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct WithinIdentity<K>(pub K);
+
+impl<K: PodKind> PodKind for WithinIdentity<K> {
+    const NAME: &'static str = K::NAME;
+    type Edge = K::Edge;
+    fn check(&self, new: &Card, existing: &Card) -> Option<Link<Self::Edge>> {
+        let link = self.0.check(new, existing)?;
+        let to_new = new.within_identity(&existing.color_identity);
+        let to_existing = existing.within_identity(&new.color_identity);
+        let dir = match (link.dir, to_new, to_existing) {
+            (LinkDirection::From, true, _) => LinkDirection::From,
+            (LinkDirection::To, _, true) => LinkDirection::To,
+            (LinkDirection::Both, true, true) => LinkDirection::Both,
+            (LinkDirection::Both, true, false) => LinkDirection::From,
+            (LinkDirection::Both, false, true) => LinkDirection::To,
+            _ => return None,
+        };
+        Some(Link {
+            edge: link.edge,
+            dir,
+        })
+    }
+
+    fn cmc_reach(&self) -> Option<u8> {
+        self.0.cmc_reach()
+    }
+
+    fn untaps(&self, card: &Card) -> bool {
+        self.0.untaps(card)
+    }
+
+    fn reaches_up_to(&self) -> bool {
+        self.0.reaches_up_to()
+    }
+}
+
+/// Which edges are drawn, by their [label](PodEdge::label), compared case
+/// insensitively. The graph keeps every edge, only rendering skips them.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EdgeFilter {
+    /// Edges with these labels aren't drawn.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hide: Vec<String>,
+    /// If not empty, only edges with one of these labels are drawn.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub only: Vec<String>,
+}
+
+impl EdgeFilter {
+    /// Whether every edge is drawn.
+    pub fn is_empty(&self) -> bool {
+        self.hide.is_empty() && self.only.is_empty()
+    }
+
+    /// Whether edges labeled `label` are drawn.
+    pub fn shows(&self, label: &str) -> bool {
+        !self.hide.iter().any(|h| h.eq_ignore_ascii_case(label))
+            && (self.only.is_empty() || self.only.iter().any(|o| o.eq_ignore_ascii_case(label)))
+    }
+}
+
+/// The shapes cards are drawn with, as pairs of a type, compared case
+/// insensitively, and a graphviz shape, like `("Artifact", "box")`. The
+/// first pair for a type wins, and cards take the shape of the first pair
+/// they have the type of. The shape `double` instead draws a second border
+/// around whatever shape the card has. By default artifacts are boxes and
+/// legendary cards have a double border.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NodeShapes(pub Vec<(String, String)>);
+
+impl Default for NodeShapes {
+    fn default() -> Self {
+        Self(vec![
+            ("Artifact".into(), "box".into()),
+            ("Legendary".into(), "double".into()),
+        ])
+    }
+}
+
+impl NodeShapes {
+    /// Whether these are the default shapes.
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// The shape of the card, if it has a type with one, and whether it has
+    /// a double border.
+    pub fn shape_of(&self, card: &Card) -> (Option<&str>, bool) {
+        let mut seen = Vec::<&str>::new();
+        let mut shape = None;
+        let mut double = false;
+        for (ty, s) in &self.0 {
+            if seen.iter().any(|t| t.eq_ignore_ascii_case(ty)) {
+                continue;
+            }
+            seen.push(ty);
+            if !card.types.iter().any(|t| t.eq_ignore_ascii_case(ty)) {
+                continue;
+            }
+            if s == "double" {
+                double = true;
+            } else {
+                shape.get_or_insert(s.as_str());
+            }
+        }
+        (shape, double)
+    }
+}
+
+/// `s` escaped to go between the double quotes of a graphviz string.
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// The color of edges with this [color key](PodEdge::color_key), as a hue
+/// picked by its hash, dark enough to read on white. Only depends on the key,
+/// so the same label has the same color in every graph.
+fn edge_color(key: &str) -> String {
+    // FNV's high bits barely change between similar keys, so they're mixed
+    // before picking the hue
+    let z = hash::mix(hash::fnv1a(key.bytes()));
+    let hue = (z as f64 / u64::MAX as f64) * 6.0;
+    let (value, saturation) = (0.7, 0.75);
+    let chroma = value * saturation;
+    let x = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+    let (r, g, b) = match hue as u8 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let byte = |c: f64| ((c + value - chroma) * 255.0).round() as u8;
+    format!("\"#{:02x}{:02x}{:02x}\"", byte(r), byte(g), byte(b))
+}
+
+/// How the cards of a rendered graph are grouped.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum ClusterBy {
+    /// By mana value
+    #[default]
+    ManaValue,
+    /// By the first of each card's decklist tags, like `#recursion`
+    Tag,
+}
+
+/// How a graph is drawn, as opposed to what's in it, so it's passed to
+/// [PodGraph::write_dot] rather than kept, or serialized, with the graph.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RenderOptions {
+    /// Which edges are drawn.
+    pub edge_filter: EdgeFilter,
+    /// How the cards are grouped.
+    pub cluster_by: ClusterBy,
+    /// Whether the graph is labeled at the bottom with its totals.
+    pub stats_footer: bool,
+    /// The shapes cards are drawn with, by type.
+    pub node_shapes: NodeShapes,
+}
+
+/// How many ways there are to climb to a payoff, from
+/// [PodGraph::redundancy].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Redundancy {
+    /// How many chains reach the payoff without sharing an edge, which is
+    /// also how many links have to be removed to cut it off.
+    pub chains: u32,
+    /// The mana values those chains start from, lowest first.
+    pub starting_cmcs: Vec<u8>,
+}
+
+/// What [PodGraph::arrival_turns] assumes about a game's mana.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManaCurve {
+    /// The mana available on each turn, from the first. Turns past the end
+    /// have as much as the last one.
+    pub per_turn: Vec<u32>,
+    /// What one activation of the engine costs, like 2 for Birthing Pod
+    /// paying the Phyrexian mana with mana.
+    pub activation_cost: u32,
+}
+
+/// Where a graph came from, written at the top of its outputs along with its
+/// card count, [PodKind::NAME] and [content hash](PodGraph::content_hash).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Metadata {
+    /// The deck's name.
+    pub name: Option<String>,
+    /// When the graph was built, in whatever format the caller prefers.
+    pub date: Option<String>,
+}
+
+/// A graph of cards, with an edge from every card to each card it can fetch
+/// through the `K` engine.
 #[derive(Debug)]
-pub(crate) struct PodGraph<K: PodKind> {
-    g: Graph<Card, K::Edge>,
-    _pod: PhantomData<K>,
+pub struct PodGraph<K: PodKind> {
+    g: StableGraph<Card, K::Edge>,
+    /// The nodes of each mana value, to only check the cards within
+    /// [PodKind::cmc_reach] of a new one.
+    by_cmc: HashMap<u8, Vec<NodeIndex>>,
+    /// The node of each card, by name, so duplicates and lookups don't scan
+    /// the whole graph.
+    by_name: HashMap<String, NodeIndex>,
+    /// The nodes that can reach each target, cleared whenever an edge
+    /// changes.
+    reachable: Mutex<HashMap<NodeIndex, Vec<NodeIndex>>>,
+    kind: K,
+    highlight: Option<String>,
+    highlight_chain: Option<Vec<String>>,
+    owned: Option<Collection>,
+    metadata: Option<Metadata>,
 }
 
-impl<K: PodKind> PodGraph<K> {
+impl<K: PodKind + Default> Default for PodGraph<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: PodKind + Clone> Clone for PodGraph<K> {
+    fn clone(&self) -> Self {
+        Self {
+            g: self.g.clone(),
+            by_cmc: self.by_cmc.clone(),
+            by_name: self.by_name.clone(),
+            reachable: Default::default(),
+            kind: self.kind.clone(),
+            highlight: self.highlight.clone(),
+            highlight_chain: self.highlight_chain.clone(),
+            owned: self.owned.clone(),
+            metadata: self.metadata.clone(),
+        }
+    }
+}
+
+impl<K: PodKind + Default> PodGraph<K> {
+    /// Creates an empty graph.
     pub fn new() -> Self {
+        Self::with_kind(K::default())
+    }
+
+    /// Starts building a graph. See [PodGraphBuilder].
+    pub fn builder() -> PodGraphBuilder<K> {
+        PodGraphBuilder::for_kind(K::default())
+    }
+}
+
+impl<K: PodKind> PodGraph<K> {
+    /// Creates an empty graph for a specific instance of an engine.
+    pub fn with_kind(kind: K) -> Self {
         Self {
             g: Default::default(),
-            _pod: PhantomData,
+            by_cmc: Default::default(),
+            by_name: Default::default(),
+            reachable: Default::default(),
+            kind,
+            highlight: None,
+            highlight_chain: None,
+            owned: None,
+            metadata: None,
+        }
+    }
+
+    /// The engine this graph was built for.
+    pub fn kind(&self) -> &K {
+        &self.kind
+    }
+
+    /// Swaps the engine without rebuilding the edges, for graphs read back
+    /// from their serialized form, which are built with `K::default()`.
+    pub fn set_kind(&mut self, kind: K) {
+        self.kind = kind;
+    }
+
+    /// The name of the card whose reachable set is highlighted when rendering.
+    pub fn highlight(&self) -> Option<&str> {
+        self.highlight.as_deref()
+    }
+
+    /// The cards the [highlight](Self::highlight) names: the card named
+    /// exactly like it or, if there's none, every card whose name contains
+    /// it, in insertion order. The first of them is the one highlighted.
+    pub fn highlight_matches(&self) -> Vec<&Card> {
+        self.highlight()
+            .map(|name| self.matching(name))
+            .unwrap_or_default()
+            .into_iter()
+            .map(|n| &self.g[n])
+            .collect()
+    }
+
+    /// The card named exactly `name` or, if there's none, every card whose
+    /// name contains it, in insertion order.
+    fn matching(&self, name: &str) -> Vec<NodeIndex> {
+        match self.find(name) {
+            Some(exact) => vec![exact],
+            None => self
+                .g
+                .node_indices()
+                .filter(|n| self.g[*n].name.contains(name))
+                .collect(),
         }
     }
 
+    /// Sets the card whose reachable set is highlighted when rendering.
+    pub fn set_highlight(&mut self, name: Option<String>) {
+        self.highlight = name;
+    }
+
+    /// The names of the cards highlighted when rendering instead of the
+    /// cards that can reach the [highlight](Self::highlight), like a chain
+    /// picked out by hand.
+    pub fn highlight_chain(&self) -> Option<&[String]> {
+        self.highlight_chain.as_deref()
+    }
+
+    /// Sets the cards highlighted when rendering. Names that aren't in the
+    /// graph are ignored.
+    pub fn set_highlight_chain(&mut self, names: Option<Vec<String>>) {
+        self.highlight_chain = names;
+    }
+
+    /// The collection cards are checked against when rendering, if any.
+    pub fn owned(&self) -> Option<&Collection> {
+        self.owned.as_ref()
+    }
+
+    /// Sets the collection cards are checked against when rendering. Cards
+    /// missing from it are drawn dashed.
+    pub fn set_owned(&mut self, owned: Option<Collection>) {
+        self.owned = owned;
+    }
+
+    /// Where the graph came from, if it was set.
+    pub fn metadata(&self) -> Option<&Metadata> {
+        self.metadata.as_ref()
+    }
+
+    /// Sets where the graph came from, to be written at the top of its
+    /// outputs.
+    pub fn set_metadata(&mut self, metadata: Option<Metadata>) {
+        self.metadata = metadata;
+    }
+
+    /// A hash of the names of the cards, in insertion order, to trace a
+    /// render back to the list it was built from. It's 64 bit FNV-1a, so it
+    /// doesn't change between versions or platforms.
+    pub fn content_hash(&self) -> u64 {
+        hash::fnv1a(self.cards().flat_map(|c| c.name.bytes().chain([b'\n'])))
+    }
+
+    /// The lines of the header written at the top of the outputs, if the
+    /// graph has [metadata](Self::metadata).
+    fn header(&self) -> Option<Vec<String>> {
+        let metadata = self.metadata()?;
+        let mut lines = Vec::new();
+        lines.extend(metadata.name.as_ref().map(|n| format!("deck: {n}")));
+        lines.extend(metadata.date.as_ref().map(|d| format!("date: {d}")));
+        lines.push(format!("cards: {}", self.g.node_count()));
+        lines.push(format!("pod: {}", K::NAME));
+        let untappers = self.untappers();
+        if !untappers.is_empty() {
+            let names = untappers.iter().map(|c| c.name.as_str());
+            lines.push(format!(
+                "activations per turn: {} ({})",
+                self.activations_per_turn(),
+                names.collect::<Vec<_>>().join(", ")
+            ));
+        }
+        lines.push(format!("hash: {:016x}", self.content_hash()));
+        Some(lines)
+    }
+
+    /// The cards in the graph, in insertion order.
+    pub fn cards(&self) -> impl Iterator<Item = &Card> {
+        self.g.node_weights()
+    }
+
+    /// The edges of the graph, as the card that fetches, the card it fetches
+    /// and how.
+    pub fn edges(&self) -> impl Iterator<Item = (&Card, &Card, &K::Edge)> {
+        (&self.g)
+            .edge_references()
+            .map(|e| (&self.g[e.source()], &self.g[e.target()], e.weight()))
+    }
+
+    /// Adds a card, connecting it to every card already in the graph. If a
+    /// card with the same name is already in the graph, the new one's
+    /// [count](Card::count) is added to it instead.
     pub fn add_card(&mut self, c: Card) {
-        let links = self
-            .g
-            .node_indices()
-            .filter_map(|n| K::check(&c, &self.g[n]).map(|l| (n, l)))
-            .collect::<Vec<_>>();
+        let Some(c) = self.merge_duplicate(c) else {
+            return;
+        };
+        debug!(name = %c.name, "added");
+        let node = self.insert(c);
+        self.link(node);
+    }
+
+    /// Adds the card's node, without any edges.
+    fn insert(&mut self, c: Card) -> NodeIndex {
+        let (cmc, name) = (c.cmc, c.name.clone());
         let node = self.g.add_node(c);
+        self.by_cmc.entry(cmc).or_default().push(node);
+        self.by_name.insert(name, node);
+        node
+    }
+
+    /// Adds the card's count to the card with the same name, if there is
+    /// one, or gives the card back.
+    fn merge_duplicate(&mut self, c: Card) -> Option<Card> {
+        let Some(node) = self.find(&c.name) else {
+            return Some(c);
+        };
+        warn!(name = %c.name, "listed more than once, merging the copies");
+        self.g[node].count += c.count;
+        None
+    }
+
+    /// Adds many cards at once. The graph ends up the same as after calling
+    /// [add_card](Self::add_card) for each of them in order, but the pairs of
+    /// cards are checked on every core.
+    pub fn add_cards(&mut self, cards: impl IntoIterator<Item = Card>) {
+        let cores = available_parallelism().map_or(1, |n| n.get());
+        self.add_cards_on(cards, cores);
+    }
+
+    /// [add_cards](Self::add_cards) on at most `cores` threads.
+    fn add_cards_on(&mut self, cards: impl IntoIterator<Item = Card>, cores: usize) {
+        let mut unique = Vec::<Card>::new();
+        let mut positions = HashMap::<String, usize>::new();
+        for c in cards {
+            let Some(c) = self.merge_duplicate(c) else {
+                continue;
+            };
+            match positions.get(&c.name) {
+                Some(&i) => {
+                    warn!(name = %c.name, "listed more than once, merging the copies");
+                    unique[i].count += c.count;
+                }
+                None => {
+                    positions.insert(c.name.clone(), unique.len());
+                    unique.push(c);
+                }
+            }
+        }
+        let cards = unique;
+        let threads = cores.min(cards.len().div_ceil(CARDS_PER_THREAD));
+        if threads <= 1 {
+            // Checking every pair up front only pays off when it's split.
+            cards.into_iter().for_each(|c| self.add_card(c));
+            return;
+        }
+        self.forget_reachability();
+        let new = cards
+            .into_iter()
+            .map(|c| {
+                debug!(name = %c.name, "added");
+                self.insert(c)
+            })
+            .collect::<Vec<_>>();
+        // Each card is only linked to the cards that were in the graph before
+        // it, like add_card would have. Cards that already were in the graph
+        // rank 0, new ones rank by the order they were added in.
+        let mut rank = vec![0; self.g.node_bound()];
+        for (i, n) in new.iter().enumerate() {
+            rank[n.index()] = i + 1;
+        }
+        let added_before =
+            |node: NodeIndex, other: NodeIndex| rank[other.index()] < rank[node.index()];
+        let this = &*self;
+        let links = debug_span!("links", threads).in_scope(|| {
+            std::thread::scope(|s| {
+                new.chunks(new.len().div_ceil(threads))
+                    .map(|chunk| {
+                        s.spawn(move || {
+                            chunk
+                                .iter()
+                                .map(|&node| {
+                                    (node, this.links(node, |other| added_before(node, other)))
+                                })
+                                .collect::<Vec<_>>()
+                        })
+                    })
+                    .collect::<Vec<_>>()
+                    .into_iter()
+                    .flat_map(|t| t.join().expect("linking threads don't panic"))
+                    .collect::<Vec<_>>()
+            })
+        });
+        for (node, links) in links {
+            self.add_links(node, links);
+        }
+    }
+
+    /// Connects `node` to every other card in the graph.
+    fn link(&mut self, node: NodeIndex) {
+        self.forget_reachability();
+        let links = self.links(node, |_| true);
+        self.add_links(node, links);
+    }
+
+    /// The links between `node` and the other cards that pass `filter`.
+    fn links(
+        &self,
+        node: NodeIndex,
+        filter: impl Fn(NodeIndex) -> bool,
+    ) -> Vec<(NodeIndex, Link<K::Edge>)> {
+        let candidates = match self.kind.cmc_reach() {
+            Some(reach) => {
+                let cmc = self.g[node].cmc;
+                let mut candidates = (cmc.saturating_sub(reach)..=cmc.saturating_add(reach))
+                    .filter_map(|cmc| self.by_cmc.get(&cmc))
+                    .flatten()
+                    .copied()
+                    .collect::<Vec<_>>();
+                candidates.sort_unstable();
+                candidates
+            }
+            None => self.g.node_indices().collect(),
+        };
+        candidates
+            .into_iter()
+            .filter(|n| *n != node && filter(*n))
+            .filter_map(|n| self.kind.check(&self.g[node], &self.g[n]).map(|l| (n, l)))
+            .collect()
+    }
+
+    fn add_links(&mut self, node: NodeIndex, links: Vec<(NodeIndex, Link<K::Edge>)>) {
         for (existing_node, link) in links {
+            trace!(
+                from = %self.g[node].name,
+                to = %self.g[existing_node].name,
+                "adding link"
+            );
             match link.dir {
                 LinkDirection::From => self.g.add_edge(existing_node, node, link.edge),
                 LinkDirection::To => self.g.add_edge(node, existing_node, link.edge),
+                LinkDirection::Both => {
+                    self.g.add_edge(existing_node, node, link.edge.clone());
+                    self.g.add_edge(node, existing_node, link.edge)
+                }
             };
         }
     }
 
-    fn nodes_that_can_reach(&self, name: &str) -> Vec<NodeIndex> {
-        let Some(target) = self.g.node_indices().find(|n| self.g[*n].name.contains(name)) else {
-            return Default::default();
+    fn find(&self, name: &str) -> Option<NodeIndex> {
+        self.by_name.get(name).copied()
+    }
+
+    /// Adds the cards of `other` that this graph doesn't have, linking them
+    /// to every card, old and new, through this graph's engine. Cards both
+    /// graphs have are kept as they are here, with their count unchanged,
+    /// and the rest of `other`, like its highlight, is dropped.
+    pub fn merge(&mut self, mut other: PodGraph<K>) {
+        let nodes = other.g.node_indices().collect::<Vec<_>>();
+        let cards = nodes
+            .into_iter()
+            .filter_map(|n| other.g.remove_node(n))
+            .filter(|c| self.find(&c.name).is_none())
+            .collect::<Vec<_>>();
+        self.add_cards(cards);
+    }
+
+    /// Removes the card with this exact name, along with its edges. The other
+    /// cards keep their node indices.
+    pub fn remove_card(&mut self, name: &str) -> Option<Card> {
+        let node = self.find(name)?;
+        debug!(name, "removed");
+        self.unindex(node);
+        self.by_name.remove(name);
+        self.forget_reachability();
+        self.g.remove_node(node)
+    }
+
+    fn unindex(&mut self, node: NodeIndex) {
+        if let Some(bucket) = self.by_cmc.get_mut(&self.g[node].cmc) {
+            bucket.retain(|n| *n != node);
+        }
+    }
+
+    /// Replaces the card with the same name as `card`, recomputing its edges,
+    /// and returns the old version. If there is no such card, `card` is added
+    /// instead.
+    pub fn update_card(&mut self, card: Card) -> Option<Card> {
+        let Some(node) = self.find(&card.name) else {
+            self.add_card(card);
+            return None;
         };
-        let mut space = DfsSpace::new(&self.g);
-        self.g
-            .node_indices()
-            .filter(|n| petgraph::algo::has_path_connecting(&self.g, *n, target, Some(&mut space)))
-            .collect()
+        debug!(name = %card.name, "updated");
+        self.unindex(node);
+        self.by_cmc.entry(card.cmc).or_default().push(node);
+        let old = std::mem::replace(&mut self.g[node], card);
+        let edges = self
+            .g
+            .edges_directed(node, Direction::Outgoing)
+            .chain(self.g.edges_directed(node, Direction::Incoming))
+            .map(|e| e.id())
+            .collect::<Vec<_>>();
+        for e in edges {
+            self.g.remove_edge(e);
+        }
+        self.link(node);
+        Some(old)
     }
 
-    pub async fn to_img<P: AsRef<Path>>(
+    /// Writes the graph as graphviz source to `path`. See
+    /// [write_dot](Self::write_dot).
+    #[cfg(feature = "fs")]
+    #[tracing::instrument(level = "debug", skip_all, fields(path = %path.as_ref().display()))]
+    pub async fn to_img<P: AsRef<std::path::Path>>(
         &self,
         path: P,
-        draw_path_to: Option<&str>,
+        options: &RenderOptions,
     ) -> io::Result<()> {
-        let highlight = draw_path_to.map(|name| self.nodes_that_can_reach(name));
-        let mut file = BufWriter::new(File::create(path).await?);
-        file.write_all(
-            b"digraph {\n    node [colorscheme=spectral11]\nedge [colorscheme=dark28]\n",
-        )
-        .await?;
-        let subgraphs = self
-            .g
-            .node_indices()
-            .fold(HashMap::<_, Vec<_>>::new(), |mut acc, n| {
-                acc.entry(self.g[n].cmc).or_default().push(n);
-                acc
-            });
-        for (cmc, subgraph) in subgraphs {
-            file.write_all(format!("    subgraph cluster_{cmc} {{\n").as_bytes())
-                .await?;
-            for n in subgraph {
-                let buf = format!(
-                    "        {} [ label = \"{}\" {style} {hi}]\n",
-                    n.index(),
-                    self.g[n].name,
-                    style = match self.node_is_isolated(&n) {
-                        true => "style=filled fillcolor=2",
-                        false => "",
-                    },
-                    hi = match &highlight {
-                        Some(highlight) if highlight.contains(&n) => "style=filled fillcolor=11",
-                        _ => "",
-                    }
+        self.write_dot(tokio::fs::File::create(path).await?, options)
+            .await
+    }
+
+    /// Renders the graph as graphviz source in memory. See
+    /// [write_dot](Self::write_dot).
+    pub async fn to_dot(&self, options: &RenderOptions) -> String {
+        let mut buf = Vec::new();
+        self.write_dot(&mut buf, options)
+            .await
+            .expect("writing to a Vec never fails");
+        String::from_utf8(buf).expect("the graph is written as utf8")
+    }
+
+    /// Writes the graph as graphviz source, drawn as `options` say: the cards
+    /// are grouped as [clustered](RenderOptions::cluster_by), edges the
+    /// [edge filter](RenderOptions::edge_filter) hides aren't drawn and cards
+    /// are shaped by their [types](RenderOptions::node_shapes). If a
+    /// [highlight](Self::highlight) is set, only that card and the cards that
+    /// can reach it are highlighted, and if a [chain](Self::highlight_chain)
+    /// is, only its cards are. If a collection is [owned](Self::owned), the
+    /// cards missing from it are dashed. Each card links to its [Scryfall
+    /// page](Card::scryfall_url), which graphviz keeps in svg output as
+    /// clickable nodes. [Commanders](Card::commander) are drawn as double
+    /// octagons and [untappers](Self::untappers) as boxes. Engines that
+    /// [reach up to](PodKind::reaches_up_to) a mana value get nested bands,
+    /// one per X, instead of a row of mana values.
+    /// [Metadata](Self::metadata) is written as a header of `//` comments and
+    /// as the graph's `comment`, which graphviz keeps in svg output. With a
+    /// [stats footer](RenderOptions::stats_footer) the graph is labeled at the
+    /// bottom with its card, edge and [orphan](Self::orphans) counts and the
+    /// length of its [longest chain](Self::longest_chain).
+    pub async fn write_dot<W: AsyncWrite + Unpin>(
+        &self,
+        w: W,
+        options: &RenderOptions,
+    ) -> io::Result<()> {
+        let highlight = debug_span!("highlight").in_scope(|| {
+            if let Some(chain) = self.highlight_chain() {
+                return Some(
+                    self.g
+                        .node_indices()
+                        .filter(|n| chain.contains(&self.g[*n].name))
+                        .collect(),
                 );
-                file.write_all(buf.as_bytes()).await?;
             }
-            file.write_all(format!("       label = \"{cmc}\"\n").as_bytes())
-                .await?;
-            file.write_all(b"   }\n").await?;
-        }
-        let mut link_color = HashMap::new();
-        for e in self.g.edge_indices() {
-            let (from, to) = self.g.edge_endpoints(e).unwrap();
-            if let Some(highlight) = &highlight {
-                if !(highlight.contains(&from) && highlight.contains(&to)) {
+            self.highlight().map(|name| {
+                self.matching(name)
+                    .first()
+                    .map(|target| self.nodes_that_can_reach(*target))
+                    .unwrap_or_default()
+                    .into_iter()
+                    .collect::<HashSet<_>>()
+            })
+        });
+        self.write_dot_highlighting(w, options, highlight, None)
+            .await
+    }
+
+    /// Renders one graphviz source per step of `chain`, each highlighting the
+    /// cards up to that step, with the step itself standing out, like a
+    /// [highlight](Self::highlight) revealed one card at a time. Names that
+    /// aren't in the graph are skipped.
+    pub async fn to_walkthrough(&self, chain: &[&str], options: &RenderOptions) -> Vec<String> {
+        let steps = chain
+            .iter()
+            .filter_map(|name| self.find(name))
+            .collect::<Vec<_>>();
+        let mut frames = Vec::with_capacity(steps.len());
+        for (i, step) in steps.iter().enumerate() {
+            let mut buf = Vec::new();
+            self.write_dot_highlighting(
+                &mut buf,
+                options,
+                Some(steps[..=i].iter().copied().collect()),
+                Some(*step),
+            )
+            .await
+            .expect("writing to a Vec never fails");
+            frames.push(String::from_utf8(buf).expect("the graph is written as utf8"));
+        }
+        frames
+    }
+
+    async fn write_dot_highlighting<W: AsyncWrite + Unpin>(
+        &self,
+        mut w: W,
+        options: &RenderOptions,
+        highlight: Option<HashSet<NodeIndex>>,
+        current: Option<NodeIndex>,
+    ) -> io::Result<()> {
+        let mut out = String::with_capacity(64 * (self.g.node_count() + self.g.edge_count()));
+        let header = self.header();
+        for line in header.iter().flatten() {
+            let _ = writeln!(out, "// {line}");
+        }
+        out.push_str("digraph {\n    node [colorscheme=spectral11]\n");
+        if let Some(header) = header {
+            // graphviz copies the comment attribute into svg output
+            let _ = writeln!(out, "    comment = \"{}\"", escape(&header.join(", ")));
+        }
+        let bands = options.cluster_by == ClusterBy::ManaValue && self.kind.reaches_up_to();
+        debug_span!("nodes").in_scope(|| {
+            let mut subgraphs = self.clusters(options.cluster_by);
+            if bands {
+                // the widest band goes first, holding every narrower one
+                subgraphs.reverse();
+            }
+            let count = subgraphs.len();
+            for (i, (id, label, subgraph)) in subgraphs.into_iter().enumerate() {
+                let _ = writeln!(out, "    subgraph cluster_{id} {{");
+                if bands {
+                    let _ = writeln!(
+                        out,
+                        "       style=filled colorscheme=spectral11 fillcolor={}",
+                        5 + i % 4
+                    );
+                }
+                for n in subgraph {
+                    let fill = match &highlight {
+                        _ if current == Some(n) => Some(1),
+                        Some(highlight) if highlight.contains(&n) => Some(11),
+                        _ if !bands && self.node_is_isolated(&n) => Some(2),
+                        _ => None,
+                    };
+                    let unowned = self.owned.as_ref().is_some_and(|o| !o.contains(&self.g[n]));
+                    let style = match (fill, unowned) {
+                        (Some(fill), false) => format!("style=filled fillcolor={fill} "),
+                        (Some(fill), true) => format!("style=\"filled,dashed\" fillcolor={fill} "),
+                        (None, true) => "style=dashed ".into(),
+                        (None, false) => String::new(),
+                    };
+                    let (by_type, double) = options.node_shapes.shape_of(&self.g[n]);
+                    let mut shape = match by_type {
+                        _ if self.g[n].commander => "shape=doubleoctagon ".into(),
+                        _ if self.kind.untaps(&self.g[n]) => "shape=box ".into(),
+                        Some(shape) => format!("shape={shape} "),
+                        None => String::new(),
+                    };
+                    if double {
+                        shape.push_str("peripheries=2 ");
+                    }
+                    let _ = writeln!(
+                        out,
+                        "        {} [ label = \"{}\" URL=\"{}\" target=\"_blank\" {style}{shape}]",
+                        n.index(),
+                        escape(&self.g[n].name),
+                        self.g[n].scryfall_url(),
+                    );
+                }
+                if bands {
+                    let _ = writeln!(out, "       label = \"X = {label}\"");
+                } else {
+                    let _ = writeln!(out, "       label = \"{label}\"");
+                    out.push_str("   }\n");
+                }
+            }
+            if bands {
+                out.push_str(&"   }\n".repeat(count));
+            }
+        });
+        debug_span!("edges").in_scope(|| {
+            for e in self.g.edge_indices() {
+                let (from, to) = self.g.edge_endpoints(e).unwrap();
+                if let Some(highlight) = &highlight {
+                    if !(highlight.contains(&from) && highlight.contains(&to)) {
+                        continue;
+                    }
+                }
+                let label = self.g[e].label();
+                if !options.edge_filter.shows(&label) {
                     continue;
                 }
+                let color = edge_color(&self.g[e].color_key());
+                let style = match self.g[e].style() {
+                    Some(style) => format!(" style={style}"),
+                    None => String::new(),
+                };
+                let _ = writeln!(
+                    out,
+                    "{} -> {} [ label = \"{}\" color={color} fontcolor={color}{style}]",
+                    from.index(),
+                    to.index(),
+                    escape(&label),
+                );
             }
-            let color_count = link_color.len();
-            let color = link_color
-                .entry(&self.g[e])
-                .or_insert_with(|| color_count + 1);
-            let buf = format!(
-                "{} -> {} [ label = \"{}\" color={color} fontcolor={color}]\n",
-                from.index(),
-                to.index(),
-                self.g[e],
+        });
+        if options.stats_footer {
+            // after the clusters, so they don't inherit the label
+            let _ = writeln!(
+                out,
+                "    labelloc=b labeljust=l label = \"{}\\l\"",
+                self.stats().join("\\l")
             );
-            file.write_all(buf.as_bytes()).await?;
         }
-        file.write_all(b"}").await?;
-        file.flush().await?;
-        Ok(())
+        out.push('}');
+        w.write_all(out.as_bytes()).await?;
+        w.flush().await
     }
 
-    fn node_is_isolated(&self, index: &NodeIndex) -> bool {
-        self.g.edge_indices().all(|e| {
-            self.g
-                .edge_endpoints(e)
-                .map(|(from, to)| *index != from && *index != to)
-                .unwrap_or_default()
-        })
+    /// The lines of the [stats footer](RenderOptions::stats_footer).
+    fn stats(&self) -> Vec<String> {
+        let longest = match self.longest_chain() {
+            Some(chain) => chain.len().to_string(),
+            None => "none, the graph has a cycle".into(),
+        };
+        vec![
+            format!("cards: {}", self.g.node_count()),
+            format!("edges: {}", self.g.edge_count()),
+            format!("orphans: {}", self.orphans().len()),
+            format!("longest chain: {longest}"),
+        ]
+    }
+
+    /// The clusters the nodes are drawn in, as their id, label and nodes, in
+    /// the order they're drawn.
+    fn clusters(&self, cluster_by: ClusterBy) -> Vec<(String, String, Vec<NodeIndex>)> {
+        match cluster_by {
+            ClusterBy::ManaValue => self
+                .g
+                .node_indices()
+                .fold(BTreeMap::<_, Vec<_>>::new(), |mut acc, n| {
+                    acc.entry(self.g[n].cmc).or_default().push(n);
+                    acc
+                })
+                .into_iter()
+                .map(|(cmc, nodes)| (cmc.to_string(), cmc.to_string(), nodes))
+                .collect(),
+            ClusterBy::Tag => self
+                .g
+                .node_indices()
+                .fold(BTreeMap::<_, Vec<_>>::new(), |mut acc, n| {
+                    acc.entry(self.g[n].tags.first()).or_default().push(n);
+                    acc
+                })
+                .into_iter()
+                .enumerate()
+                .map(|(i, (tag, nodes))| {
+                    let label = tag.map_or("untagged".into(), |t| escape(t));
+                    (format!("tag_{i}"), label, nodes)
+                })
+                .collect(),
+        }
+    }
+
+    /// Writes the cards back out as a decklist, one group per set of
+    /// [connected](Self::components) cards, each climbing the mana value
+    /// ladder. Cards that aren't connected to any other come last. Engines
+    /// that [reach up to](PodKind::reaches_up_to) a mana value are grouped by
+    /// the X that first reaches each card instead. Group headers are `//`
+    /// comments, so the result can be read back in.
+    pub fn to_decklist(&self) -> String {
+        if self.kind.reaches_up_to() {
+            return self.to_tiered_decklist();
+        }
+        let (mut chains, orphans): (Vec<_>, Vec<_>) =
+            self.components().into_iter().partition(|c| c.len() > 1);
+        let mut out = String::new();
+        for (i, chain) in chains.iter_mut().enumerate() {
+            chain.sort_by_key(|c| c.cmc);
+            let (first, last) = (chain[0].cmc, chain[chain.len() - 1].cmc);
+            let _ = writeln!(out, "// Chain {}: mana value {first} to {last}", i + 1);
+            for card in chain {
+                let _ = writeln!(out, "{} {}", card.count, card.name);
+            }
+            out.push('\n');
+        }
+        if !orphans.is_empty() {
+            out.push_str("// Unconnected\n");
+            let mut orphans = orphans.into_iter().flatten().collect::<Vec<_>>();
+            orphans.sort_by_key(|c| c.cmc);
+            for card in orphans {
+                let _ = writeln!(out, "{} {}", card.count, card.name);
+            }
+        }
+        out
+    }
+
+    fn to_tiered_decklist(&self) -> String {
+        let mut out = String::new();
+        let mut cards = self.cards().collect::<Vec<_>>();
+        cards.sort_by_key(|c| c.cmc);
+        for (i, card) in cards.iter().enumerate() {
+            if i == 0 || cards[i - 1].cmc != card.cmc {
+                if i > 0 {
+                    out.push('\n');
+                }
+                let _ = writeln!(out, "// X = {}", card.cmc);
+            }
+            let _ = writeln!(out, "{} {}", card.count, card.name);
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use futures::executor::block_on;
+
+    use super::*;
+
+    fn creature(name: &str, cmc: u8, ty: &str) -> Card {
+        serde_json::from_value(serde_json::json!({
+            "name": name,
+            "cmc": cmc,
+            "types": ["Creature", ty],
+        }))
+        .expect("a valid card")
+    }
+
+    /// A pair of creatures sharing each of `types`, one a mana value above
+    /// the other, so every type labels one edge.
+    fn pairs(types: &[String]) -> PodGraph<PyreOfHeroes> {
+        let mut graph = PodGraph::new();
+        graph.add_cards(types.iter().flat_map(|ty| {
+            [
+                creature(&format!("{ty} 1"), 1, ty),
+                creature(&format!("{ty} 2"), 2, ty),
+            ]
+        }));
+        graph
+    }
+
+    /// The edges as the names of the cards at either end and the label,
+    /// sorted.
+    fn edge_list<K: PodKind>(graph: &PodGraph<K>) -> Vec<(String, String, String)> {
+        let mut edges = graph
+            .edges()
+            .map(|(from, to, e)| (from.name.clone(), to.name.clone(), e.label()))
+            .collect::<Vec<_>>();
+        edges.sort();
+        edges
+    }
+
+    fn edge(from: &str, to: &str, label: &str) -> (String, String, String) {
+        (from.into(), to.into(), label.into())
+    }
+
+    /// The color of each edge label in the graphviz source.
+    fn edge_colors(dot: &str) -> HashMap<String, String> {
+        dot.lines()
+            .filter(|l| l.contains(" -> "))
+            .map(|l| {
+                let label = l.split("label = \"").nth(1).unwrap().split('"').next();
+                let color = l.split(" color=").nth(1).unwrap().split(' ').next();
+                (label.unwrap().to_owned(), color.unwrap().to_owned())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn every_edge_label_gets_a_stable_color() {
+        let types = (0..40).map(|i| format!("Type{i}")).collect::<Vec<_>>();
+        let colors = edge_colors(&block_on(pairs(&types).to_dot(&RenderOptions::default())));
+        assert_eq!(colors.len(), types.len());
+        for color in colors.values() {
+            let hex = color
+                .strip_prefix("\"#")
+                .and_then(|c| c.strip_suffix('"'))
+                .unwrap_or_else(|| panic!("{color} isn't a quoted hex color"));
+            assert!(
+                hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()),
+                "{color} isn't a quoted hex color"
+            );
+        }
+
+        let mut reversed = types.clone();
+        reversed.reverse();
+        let again = edge_colors(&block_on(
+            pairs(&reversed[..10]).to_dot(&RenderOptions::default()),
+        ));
+        for (label, color) in again {
+            assert_eq!(colors[&label], color, "{label} changed color");
+        }
+    }
+
+    #[test]
+    fn duplicates_are_merged() {
+        let mut graph = PodGraph::<BirthingPod>::new();
+        graph.add_card(creature("Elf", 1, "Elf"));
+        graph.add_cards([
+            creature("Elf", 1, "Elf"),
+            creature("Goblin", 2, "Goblin"),
+            creature("Goblin", 2, "Goblin"),
+        ]);
+        graph.add_card(creature("Elf", 1, "Elf"));
+        let counts = graph
+            .cards()
+            .map(|c| (c.name.as_str(), c.count))
+            .collect::<Vec<_>>();
+        assert_eq!(counts, [("Elf", 3), ("Goblin", 2)]);
+        assert_eq!(graph.edges().count(), 1);
+    }
+
+    #[test]
+    fn adding_cards_in_parallel_links_them_like_one_at_a_time() {
+        let types = ["Elf", "Goblin", "Wizard", "Zombie", "Sliver"];
+        let cards = (0..CARDS_PER_THREAD * 4)
+            .map(|i| creature(&format!("Card {i}"), (i % 6) as u8, types[i % types.len()]))
+            .collect::<Vec<_>>();
+        let mut parallel = PodGraph::<PyreOfHeroes>::new();
+        parallel.add_card(cards[0].clone());
+        parallel.add_cards_on(cards[1..].iter().cloned(), 4);
+        let mut sequential = PodGraph::<PyreOfHeroes>::new();
+        cards.into_iter().for_each(|c| sequential.add_card(c));
+
+        assert!(!edge_list(&sequential).is_empty());
+        assert_eq!(edge_list(&parallel), edge_list(&sequential));
+    }
+
+    #[test]
+    fn removed_cards_take_their_edges_with_them() {
+        let mut graph = PodGraph::<PyreOfHeroes>::new();
+        graph.add_cards([
+            creature("One", 1, "Elf"),
+            creature("Two", 2, "Elf"),
+            creature("Three", 3, "Elf"),
+        ]);
+        let removed = graph.remove_card("Two").expect("Two is in the graph");
+        assert_eq!(removed.name, "Two");
+        assert!(edge_list(&graph).is_empty());
+        assert!(graph.remove_card("Two").is_none());
+
+        graph.add_card(removed);
+        assert_eq!(
+            edge_list(&graph),
+            [edge("One", "Two", "Elf"), edge("Two", "Three", "Elf")]
+        );
+    }
+
+    #[test]
+    fn updated_cards_are_linked_again() {
+        let mut graph = PodGraph::<PyreOfHeroes>::new();
+        graph.add_cards([
+            creature("One", 1, "Elf"),
+            creature("Two", 2, "Elf"),
+            creature("Three", 3, "Goblin"),
+        ]);
+        assert_eq!(edge_list(&graph), [edge("One", "Two", "Elf")]);
+
+        let old = graph
+            .update_card(creature("Two", 2, "Goblin"))
+            .expect("Two is in the graph");
+        assert_eq!(old.subtypes().map(|t| &**t).collect::<Vec<_>>(), ["Elf"]);
+        assert_eq!(edge_list(&graph), [edge("Two", "Three", "Goblin")]);
+
+        assert!(graph.update_card(creature("Four", 4, "Goblin")).is_none());
+        assert_eq!(
+            edge_list(&graph),
+            [
+                edge("Three", "Four", "Goblin"),
+                edge("Two", "Three", "Goblin")
+            ]
+        );
+    }
+
+    #[test]
+    fn json_keeps_everything_but_the_kind() {
+        let mut graph = PodGraph::with_kind(PyreOfHeroes {
+            match_on: MatchOn::AnyType,
+            all_shared_types: true,
+        });
+        graph.add_cards([
+            creature("One", 1, "Elf"),
+            creature("Gone", 1, "Elf"),
+            creature("Two", 2, "Elf"),
+        ]);
+        graph.remove_card("Gone");
+        graph.set_highlight(Some("Two".into()));
+
+        let json = serde_json::to_string(&graph).expect("graphs serialize");
+        let mut back = serde_json::from_str::<PodGraph<PyreOfHeroes>>(&json)
+            .expect("serialized graphs deserialize");
+        assert_eq!(
+            back.cards().map(|c| &c.name).collect::<Vec<_>>(),
+            ["One", "Two"]
+        );
+        assert_eq!(edge_list(&back), edge_list(&graph));
+        assert_eq!(back.highlight(), Some("Two"));
+        assert_eq!(back.kind().match_on, MatchOn::default());
+        assert!(!back.kind().all_shared_types);
+        assert!(serde_json::from_str::<PodGraph<BirthingPod>>(&json).is_err());
+
+        assert!(back.remove_card("One").is_some());
+        assert!(edge_list(&back).is_empty());
+    }
+
+    #[test]
+    fn merging_adds_only_the_new_cards() {
+        let mut graph = PodGraph::<PyreOfHeroes>::new();
+        graph.add_card(creature("One", 1, "Elf"));
+        let mut other = PodGraph::<PyreOfHeroes>::new();
+        other.add_cards([
+            creature("One", 1, "Elf"),
+            creature("One", 1, "Elf"),
+            creature("Two", 2, "Elf"),
+        ]);
+        other.set_highlight(Some("Two".into()));
+
+        graph.merge(other);
+        let counts = graph
+            .cards()
+            .map(|c| (c.name.as_str(), c.count))
+            .collect::<Vec<_>>();
+        assert_eq!(counts, [("One", 1), ("Two", 1)]);
+        assert_eq!(edge_list(&graph), [edge("One", "Two", "Elf")]);
+        assert_eq!(graph.highlight(), None);
+    }
+
+    #[test]
+    fn quotes_in_names_are_escaped() {
+        let mut graph = PodGraph::<BirthingPod>::new();
+        graph.add_card(creature("Kongming, \"Sleeping Dragon\"", 5, "Human"));
+        let dot = block_on(graph.to_dot(&RenderOptions::default()));
+        assert!(
+            dot.contains(r#"label = "Kongming, \"Sleeping Dragon\"""#),
+            "{dot}"
+        );
     }
 }
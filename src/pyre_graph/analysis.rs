@@ -0,0 +1,635 @@
+//! Queries over a built graph.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+
+use petgraph::{
+    algo::toposort,
+    prelude::NodeIndex,
+    unionfind::UnionFind,
+    visit::{Bfs, EdgeRef, IntoEdgeReferences, NodeIndexable, Reversed},
+    Direction,
+};
+
+use super::{ManaCurve, PodGraph, PodKind, Redundancy};
+use crate::Card;
+
+impl<K: PodKind> PodGraph<K> {
+    /// The nodes with a path to `target`, including itself, in insertion
+    /// order. Found with a single search against the edges and remembered
+    /// until the graph changes.
+    pub(super) fn nodes_that_can_reach(&self, target: NodeIndex) -> Vec<NodeIndex> {
+        let mut reachable = self.reachable.lock().unwrap_or_else(|e| e.into_inner());
+        reachable
+            .entry(target)
+            .or_insert_with(|| {
+                let mut bfs = Bfs::new(Reversed(&self.g), target);
+                let mut nodes = Vec::new();
+                while let Some(n) = bfs.next(Reversed(&self.g)) {
+                    nodes.push(n);
+                }
+                nodes.sort_unstable();
+                nodes
+            })
+            .clone()
+    }
+
+    pub(super) fn forget_reachability(&mut self) {
+        self.reachable
+            .get_mut()
+            .unwrap_or_else(|e| e.into_inner())
+            .clear();
+    }
+
+    pub(super) fn node_is_isolated(&self, index: &NodeIndex) -> bool {
+        self.g.neighbors_undirected(*index).next().is_none()
+    }
+
+    fn neighbors(&self, name: &str, dir: Direction) -> Option<Vec<&Card>> {
+        let node = self.find(name)?;
+        Some(
+            self.g
+                .edges_directed(node, dir)
+                .map(|e| match dir {
+                    Direction::Outgoing => &self.g[e.target()],
+                    Direction::Incoming => &self.g[e.source()],
+                })
+                .collect(),
+        )
+    }
+
+    /// The cards the named card can fetch directly, or `None` if there is no
+    /// card with that exact name.
+    pub fn can_fetch(&self, name: &str) -> Option<Vec<&Card>> {
+        self.neighbors(name, Direction::Outgoing)
+    }
+
+    /// The cards that can fetch the named card directly, or `None` if there is
+    /// no card with that exact name.
+    pub fn fetched_by(&self, name: &str) -> Option<Vec<&Card>> {
+        self.neighbors(name, Direction::Incoming)
+    }
+
+    /// The cards that can eventually fetch the named card, including itself,
+    /// or `None` if there is no card with that exact name.
+    pub fn cards_that_can_reach(&self, name: &str) -> Option<Vec<&Card>> {
+        let target = self.find(name)?;
+        Some(
+            self.nodes_that_can_reach(target)
+                .into_iter()
+                .map(|n| &self.g[n])
+                .collect(),
+        )
+    }
+
+    /// The tops of the chains: cards some card can fetch that can't fetch
+    /// anything themselves, in insertion order.
+    pub fn chain_tops(&self) -> Vec<&Card> {
+        self.g
+            .node_indices()
+            .filter(|n| {
+                self.g
+                    .edges_directed(*n, Direction::Incoming)
+                    .next()
+                    .is_some()
+                    && self
+                        .g
+                        .edges_directed(*n, Direction::Outgoing)
+                        .next()
+                        .is_none()
+            })
+            .map(|n| &self.g[n])
+            .collect()
+    }
+
+    /// The cards that can't fetch, nor be fetched by, any other card.
+    pub fn orphans(&self) -> Vec<&Card> {
+        self.g
+            .node_indices()
+            .filter(|n| self.node_is_isolated(n))
+            .map(|n| &self.g[n])
+            .collect()
+    }
+
+    /// The cards with no other card at a mana value the engine could move
+    /// them to, like a lone 7 drop in a deck whose curve stops at 5 when
+    /// podding ±1. The engine can't fetch them nor fetch off of them,
+    /// whatever their types, so they are usually a deckbuilding mistake.
+    /// Empty for engines without a [reach](PodKind::cmc_reach), and for
+    /// engines that [reach up to](PodKind::reaches_up_to) a mana value, which
+    /// can fetch any card below it.
+    pub fn stranded(&self) -> Vec<&Card> {
+        let Some(reach) = self.kind.cmc_reach().filter(|_| !self.kind.reaches_up_to()) else {
+            return Vec::new();
+        };
+        let neighbours = |a: &Card, b: &Card| match a.cmc.abs_diff(b.cmc) {
+            0 => reach == 0 && a.name != b.name,
+            diff => diff <= reach,
+        };
+        self.cards()
+            .filter(|a| !self.cards().any(|b| neighbours(a, b)))
+            .collect()
+    }
+
+    /// The longest sequence of cards where each one can fetch the next, or
+    /// `None` if the graph has a cycle. Ties are broken by insertion order.
+    pub fn longest_chain(&self) -> Option<Vec<&Card>> {
+        let order = toposort(&self.g, None).ok()?;
+        let mut best = HashMap::<NodeIndex, (usize, Option<NodeIndex>)>::new();
+        for &n in &order {
+            let from = self
+                .g
+                .edges_directed(n, Direction::Incoming)
+                .map(|e| (best[&e.source()].0 + 1, Some(e.source())))
+                .max_by_key(|(len, from)| (*len, from.map(std::cmp::Reverse)))
+                .unwrap_or((1, None));
+            best.insert(n, from);
+        }
+        let mut end = self
+            .g
+            .node_indices()
+            .max_by_key(|n| (best[n].0, std::cmp::Reverse(*n)));
+        let mut chain = Vec::new();
+        while let Some(n) = end {
+            chain.push(&self.g[n]);
+            end = best[&n].1;
+        }
+        chain.reverse();
+        Some(chain)
+    }
+
+    /// The most tops of chains, cards nothing is fetched with, that one game
+    /// can assemble, as one way of assembling that many. Fetching a card
+    /// sacrifices the card fetched with, so every copy of a card is climbed
+    /// through at most once: the chains are a maximum flow from the bottoms
+    /// of the chains, cards nothing fetches, where each card carries as many
+    /// chains as its [count](Card::count) and each top counts once. Cards
+    /// that all fetch each other, like in a [Transmute](crate::Transmute)
+    /// graph, have neither bottoms nor tops, so there are no payoffs.
+    pub fn payoffs_in_one_game(&self) -> Vec<&Card> {
+        // card i enters at 2i and leaves at 2i + 1, the edge between them
+        // carrying the copies of the card
+        let bound = self.g.node_bound();
+        let (source, sink) = (2 * bound, 2 * bound + 1);
+        let mut flow = Flow::new(2 * bound + 2);
+        for n in self.g.node_indices() {
+            let i = self.g.to_index(n);
+            flow.add(2 * i, 2 * i + 1, self.g[n].count);
+            let fetched = self
+                .g
+                .edges_directed(n, Direction::Incoming)
+                .next()
+                .is_some();
+            let fetches = self
+                .g
+                .edges_directed(n, Direction::Outgoing)
+                .next()
+                .is_some();
+            match (fetched, fetches) {
+                (false, true) => flow.add(source, 2 * i, u32::MAX),
+                (true, false) => flow.add(2 * i + 1, sink, 1),
+                _ => {}
+            }
+        }
+        for e in (&self.g).edge_references() {
+            let (from, to) = (self.g.to_index(e.source()), self.g.to_index(e.target()));
+            flow.add(2 * from + 1, 2 * to, u32::MAX);
+        }
+        flow.maximize(source, sink);
+        self.g
+            .node_indices()
+            .filter(|n| flow.through(2 * self.g.to_index(*n) + 1, sink) > 0)
+            .map(|n| &self.g[n])
+            .collect()
+    }
+
+    /// How hard it is to cut the named payoff off: how many chains that
+    /// share no edge climb to it from the bottoms of the chains, cards
+    /// nothing fetches, and from which mana values those chains start. Every
+    /// chain has to lose a link before the payoff is out of reach. When every
+    /// card that climbs to the payoff is fetched by another, like in a
+    /// [Transmute](crate::Transmute) graph where the cards fetch each other,
+    /// there are no bottoms and the chains start from any of those cards.
+    /// `None` if there's no card with that exact name.
+    pub fn redundancy(&self, payoff: &str) -> Option<Redundancy> {
+        let target = self.find(payoff)?;
+        let bound = self.g.node_bound();
+        let (source, sink) = (bound, self.g.to_index(target));
+        let mut flow = Flow::new(bound + 1);
+        let climbers = self
+            .nodes_that_can_reach(target)
+            .into_iter()
+            .filter(|n| *n != target)
+            .collect::<Vec<_>>();
+        let bottoms = climbers
+            .iter()
+            .copied()
+            .filter(|n| {
+                self.g
+                    .edges_directed(*n, Direction::Incoming)
+                    .next()
+                    .is_none()
+            })
+            .collect::<Vec<_>>();
+        let starts = match bottoms.is_empty() {
+            true => climbers,
+            false => bottoms,
+        };
+        for n in &starts {
+            flow.add(source, self.g.to_index(*n), u32::MAX);
+        }
+        for e in (&self.g).edge_references() {
+            flow.add(self.g.to_index(e.source()), self.g.to_index(e.target()), 1);
+        }
+        let chains = flow.maximize(source, sink);
+        let starting_cmcs = starts
+            .into_iter()
+            .filter(|n| flow.through(source, self.g.to_index(*n)) > 0)
+            .map(|n| self.g[n].cmc)
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        Some(Redundancy {
+            chains,
+            starting_cmcs,
+        })
+    }
+
+    /// Groups the cards into sets that are connected to each other, ignoring
+    /// which way the edges point. Both the groups and the cards in them are in
+    /// insertion order.
+    pub fn components(&self) -> Vec<Vec<&Card>> {
+        let mut sets = UnionFind::new(self.g.node_bound());
+        for e in (&self.g).edge_references() {
+            sets.union(self.g.to_index(e.source()), self.g.to_index(e.target()));
+        }
+        let mut components = Vec::<Vec<&Card>>::new();
+        let mut by_root = HashMap::new();
+        for n in self.g.node_indices() {
+            let root = sets.find(self.g.to_index(n));
+            let i = *by_root.entry(root).or_insert_with(|| {
+                components.push(Vec::new());
+                components.len() - 1
+            });
+            components[i].push(&self.g[n]);
+        }
+        components
+    }
+
+    /// How many cards of each subtype there are at each mana value. Cards
+    /// with [every creature type](Card::is_every_creature_type) are left
+    /// out, they'd be counted under every type.
+    pub fn type_census(&self) -> BTreeMap<&str, BTreeMap<u8, usize>> {
+        let mut census = BTreeMap::<&str, BTreeMap<u8, usize>>::new();
+        for card in self.cards().filter(|c| !c.is_every_creature_type()) {
+            let mut seen = BTreeSet::new();
+            for ty in card.subtypes().filter(|t| seen.insert(&***t)) {
+                *census.entry(ty).or_default().entry(card.cmc).or_default() += 1;
+            }
+        }
+        census
+    }
+
+    /// The cards that let the engine be [activated again](PodKind::untaps)
+    /// in the same turn, in insertion order.
+    pub fn untappers(&self) -> Vec<&Card> {
+        self.cards().filter(|c| self.kind.untaps(c)).collect()
+    }
+
+    /// How many times the engine can be activated in a turn, assuming every
+    /// [untapper](Self::untappers) is on the battlefield and untaps it once.
+    pub fn activations_per_turn(&self) -> usize {
+        1 + self.untappers().len()
+    }
+
+    /// How many turns of [activations](Self::activations_per_turn) it takes
+    /// to climb the [longest chain](Self::longest_chain) from its first card
+    /// to its last, or `None` if the graph has a cycle.
+    pub fn turns_to_climb(&self) -> Option<usize> {
+        let steps = self.longest_chain()?.len().saturating_sub(1);
+        Some(steps.div_ceil(self.activations_per_turn()))
+    }
+
+    /// The earliest turn each card `start` can climb to hits the
+    /// battlefield, with `start` there from `start_turn` on and the engine
+    /// activated as often as `mana` pays for, up to [activations per
+    /// turn](Self::activations_per_turn). In order of arrival, `start`
+    /// first, leaving out cards the mana never gets to. `None` if there's no
+    /// card named `start`.
+    pub fn arrival_turns(
+        &self,
+        start: &str,
+        start_turn: u32,
+        mana: &ManaCurve,
+    ) -> Option<Vec<(&Card, u32)>> {
+        let start = self.find(start)?;
+        let mut steps = HashMap::from([(start, 0)]);
+        let mut queue = VecDeque::from([start]);
+        while let Some(n) = queue.pop_front() {
+            for next in self.g.neighbors_directed(n, Direction::Outgoing) {
+                if !steps.contains_key(&next) {
+                    steps.insert(next, steps[&n] + 1);
+                    queue.push_back(next);
+                }
+            }
+        }
+        let most = steps.values().copied().max().unwrap_or(0);
+        // the turn each number of activations is done by
+        let mut turn_of = vec![start_turn];
+        let mut turn = start_turn;
+        while turn_of.len() <= most {
+            let i = (turn as usize).saturating_sub(1);
+            let available = mana
+                .per_turn
+                .get(i)
+                .or(mana.per_turn.last())
+                .copied()
+                .unwrap_or(0);
+            let activations = match mana.activation_cost {
+                0 => self.activations_per_turn(),
+                cost => (available / cost) as usize,
+            }
+            .min(self.activations_per_turn());
+            if activations == 0 && i + 1 >= mana.per_turn.len() {
+                break;
+            }
+            turn_of.extend(std::iter::repeat_n(turn, activations));
+            turn += 1;
+        }
+        let mut arrivals = self
+            .g
+            .node_indices()
+            .filter_map(|n| Some((n, *turn_of.get(*steps.get(&n)?)?)))
+            .collect::<Vec<_>>();
+        arrivals.sort_by_key(|(n, turn)| (*turn, steps[n]));
+        Some(
+            arrivals
+                .into_iter()
+                .map(|(n, turn)| (&self.g[n], turn))
+                .collect(),
+        )
+    }
+
+    /// The cards an X of `x` reaches: every card with a mana value of at most
+    /// `x`, in insertion order.
+    pub fn reachable_for(&self, x: u8) -> Vec<&Card> {
+        self.cards().filter(|c| c.cmc <= x).collect()
+    }
+
+    /// For each mana value in the graph, from the lowest, the cards
+    /// [reachable](Self::reachable_for) with that X.
+    pub fn reach_tiers(&self) -> Vec<(u8, Vec<&Card>)> {
+        self.cards()
+            .map(|c| c.cmc)
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .map(|x| (x, self.reachable_for(x)))
+            .collect()
+    }
+
+    /// The [component](Self::components) of the card `name` picks, matched
+    /// like the [highlight](Self::highlight_matches): the card named exactly
+    /// like it or else the first whose name contains it. `None` if no card
+    /// matches.
+    pub fn component_of(&self, name: &str) -> Option<Vec<&Card>> {
+        let card = &self.g[*self.matching(name).first()?];
+        self.components()
+            .into_iter()
+            .find(|c| c.iter().any(|other| other.name == card.name))
+    }
+}
+
+/// A flow network over numbered nodes, for the analyses that are maximum
+/// flows. Edmonds-Karp, which is plenty for a deck's worth of cards.
+struct Flow {
+    /// What's left of each edge's capacity, with the reverse of every edge
+    /// holding the flow through it.
+    /// Ordered, so that ties between flows are always broken the same way.
+    residual: Vec<BTreeMap<usize, u32>>,
+    /// Every edge's capacity, to tell flow apart from unused capacity.
+    capacity: HashMap<(usize, usize), u32>,
+}
+
+impl Flow {
+    fn new(nodes: usize) -> Self {
+        Self {
+            residual: vec![BTreeMap::new(); nodes],
+            capacity: HashMap::new(),
+        }
+    }
+
+    fn add(&mut self, from: usize, to: usize, capacity: u32) {
+        let total = self.capacity.entry((from, to)).or_default();
+        *total = total.saturating_add(capacity);
+        let left = self.residual[from].entry(to).or_default();
+        *left = left.saturating_add(capacity);
+        self.residual[to].entry(from).or_default();
+    }
+
+    /// Pushes as much flow from `source` to `sink` as fits, returning how
+    /// much that is.
+    fn maximize(&mut self, source: usize, sink: usize) -> u32 {
+        let mut total = 0u32;
+        loop {
+            let mut parent = vec![None; self.residual.len()];
+            let mut queue = VecDeque::from([source]);
+            while let Some(u) = queue.pop_front() {
+                for (&v, &left) in &self.residual[u] {
+                    if left > 0 && v != source && parent[v].is_none() {
+                        parent[v] = Some(u);
+                        queue.push_back(v);
+                    }
+                }
+            }
+            if parent[sink].is_none() {
+                return total;
+            }
+            let mut pushed = u32::MAX;
+            let mut v = sink;
+            while let Some(u) = parent[v] {
+                pushed = pushed.min(self.residual[u][&v]);
+                v = u;
+            }
+            let mut v = sink;
+            while let Some(u) = parent[v] {
+                if let Some(left) = self.residual[u].get_mut(&v) {
+                    *left -= pushed;
+                }
+                if let Some(back) = self.residual[v].get_mut(&u) {
+                    *back = back.saturating_add(pushed);
+                }
+                v = u;
+            }
+            total = total.saturating_add(pushed);
+        }
+    }
+
+    /// The flow through the edge from `from` to `to`.
+    fn through(&self, from: usize, to: usize) -> u32 {
+        let capacity = self.capacity.get(&(from, to)).copied().unwrap_or(0);
+        capacity
+            - self.residual[from]
+                .get(&to)
+                .copied()
+                .unwrap_or(0)
+                .min(capacity)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{BirthingPod, Card, ManaCurve, PodGraph, Redundancy, Transmute, XSpell};
+
+    fn creature(name: &str, cmc: u8) -> Card {
+        copies(name, cmc, 1)
+    }
+
+    fn copies(name: &str, cmc: u8, count: u32) -> Card {
+        serde_json::from_value(serde_json::json!({
+            "name": name,
+            "cmc": cmc,
+            "types": ["Creature", "Elf"],
+            "count": count,
+        }))
+        .expect("a valid card")
+    }
+
+    fn curve() -> Vec<Card> {
+        vec![
+            creature("One", 1),
+            creature("Two", 2),
+            creature("Three", 3),
+            creature("Seven", 7),
+        ]
+    }
+
+    fn names(cards: Vec<&Card>) -> Vec<&str> {
+        cards.into_iter().map(|c| c.name.as_str()).collect()
+    }
+
+    #[test]
+    fn lone_mana_values_are_stranded() {
+        let mut graph = PodGraph::<BirthingPod>::new();
+        graph.add_cards(curve());
+        assert_eq!(names(graph.stranded()), ["Seven"]);
+    }
+
+    #[test]
+    fn nothing_is_stranded_when_reaching_up_to() {
+        let mut graph = PodGraph::<XSpell>::new();
+        graph.add_cards(curve());
+        assert!(graph.stranded().is_empty());
+    }
+
+    /// One at the bottom, climbing through Two to either of two tops.
+    fn fork(copies_of_two: u32) -> PodGraph<BirthingPod> {
+        let mut graph = PodGraph::new();
+        graph.add_cards([
+            copies("One", 1, copies_of_two),
+            copies("Two", 2, copies_of_two),
+            creature("Left", 3),
+            creature("Right", 3),
+        ]);
+        graph
+    }
+
+    #[test]
+    fn one_copy_climbs_to_one_payoff() {
+        assert_eq!(names(fork(1).payoffs_in_one_game()), ["Left"]);
+    }
+
+    #[test]
+    fn every_copy_climbs_to_a_payoff() {
+        assert_eq!(names(fork(2).payoffs_in_one_game()), ["Left", "Right"]);
+    }
+
+    #[test]
+    fn cards_fetching_each_other_have_no_payoffs() {
+        let mut graph = PodGraph::<Transmute>::new();
+        graph.add_cards([creature("A", 2), creature("B", 2), creature("C", 2)]);
+        assert!(graph.payoffs_in_one_game().is_empty());
+    }
+
+    #[test]
+    fn redundancy_counts_chains_sharing_no_edge() {
+        let mut graph = PodGraph::<BirthingPod>::new();
+        graph.add_cards([
+            creature("One", 1),
+            creature("Other One", 1),
+            creature("Two", 2),
+            creature("Other Two", 2),
+            creature("Three", 3),
+        ]);
+        assert_eq!(
+            graph.redundancy("Three"),
+            Some(Redundancy {
+                chains: 2,
+                starting_cmcs: vec![1],
+            })
+        );
+        assert_eq!(graph.redundancy("Four"), None);
+    }
+
+    #[test]
+    fn redundancy_without_bottoms_starts_anywhere() {
+        let mut graph = PodGraph::<Transmute>::new();
+        graph.add_cards([creature("A", 2), creature("B", 2), creature("C", 2)]);
+        assert_eq!(
+            graph.redundancy("C"),
+            Some(Redundancy {
+                chains: 2,
+                starting_cmcs: vec![2],
+            })
+        );
+    }
+
+    #[test]
+    fn arrivals_wait_for_mana() {
+        let mut graph = PodGraph::<BirthingPod>::new();
+        graph.add_cards(curve());
+        let mana = ManaCurve {
+            per_turn: vec![2, 1, 2],
+            activation_cost: 2,
+        };
+        let arrivals = graph
+            .arrival_turns("One", 1, &mana)
+            .expect("One is in the graph")
+            .into_iter()
+            .map(|(c, turn)| (c.name.as_str(), turn))
+            .collect::<Vec<_>>();
+        assert_eq!(arrivals, [("One", 1), ("Two", 1), ("Three", 3)]);
+    }
+
+    #[test]
+    fn no_mana_arrives_nowhere() {
+        let mut graph = PodGraph::<BirthingPod>::new();
+        graph.add_cards(curve());
+        let mana = ManaCurve {
+            per_turn: vec![0],
+            activation_cost: 2,
+        };
+        let arrivals = graph
+            .arrival_turns("One", 1, &mana)
+            .expect("One is in the graph");
+        assert_eq!(
+            names(arrivals.into_iter().map(|(c, _)| c).collect()),
+            ["One"]
+        );
+        assert_eq!(graph.arrival_turns("Eight", 1, &mana), None);
+    }
+
+    #[test]
+    fn components_are_found_like_the_highlight() {
+        let mut graph = PodGraph::<BirthingPod>::new();
+        graph.add_cards(curve());
+        graph.add_card(creature("Eight", 8));
+        assert_eq!(
+            graph.component_of("Two").map(names),
+            Some(vec!["One", "Two", "Three"])
+        );
+        assert_eq!(
+            graph.component_of("Eigh").map(names),
+            Some(vec!["Seven", "Eight"])
+        );
+        assert_eq!(graph.component_of("eight"), None);
+    }
+}
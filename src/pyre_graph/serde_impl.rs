@@ -0,0 +1,162 @@
+//! Graphs are serialized as a flat list of cards and a list of edges between
+//! positions in that list, tagged with the [PodKind::NAME] they were built
+//! for:
+//!
+//! ```json
+//! {
+//!   "kind": "pyre-of-heroes",
+//!   "highlight": null,
+//!   "cards": [{ "name": "Llanowar Elves", "cmc": 1, "types": ["Elf", "Druid"] }, ...],
+//!   "edges": [{ "from": 0, "to": 1, "label": "Elf" }, ...]
+//! }
+//! ```
+//!
+//! Graphs with a [highlighted chain](PodGraph::highlight_chain) also have a
+//! `highlight_chain` list of its names, graphs with an
+//! [owned](PodGraph::owned) collection have an `owned` list of its names,
+//! and graphs with [metadata](PodGraph::metadata) have a `metadata` object
+//! with the deck's name and date, its card count and its
+//! [content hash](PodGraph::content_hash). The count and hash are recomputed
+//! when reading a graph back. How the graph is
+//! [rendered](super::RenderOptions) isn't part of it.
+
+use std::collections::HashMap;
+
+use petgraph::stable_graph::StableGraph;
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+
+use super::{Metadata, PodGraph, PodKind};
+use crate::{Card, Collection};
+
+#[derive(Serialize, Deserialize)]
+struct Edge<E> {
+    from: usize,
+    to: usize,
+    label: E,
+}
+
+#[derive(Serialize)]
+struct SerMetadata<'g> {
+    name: Option<&'g str>,
+    date: Option<&'g str>,
+    cards: usize,
+    hash: String,
+}
+
+#[derive(Serialize)]
+struct SerGraph<'g, E> {
+    kind: &'static str,
+    highlight: Option<&'g str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    highlight_chain: Option<&'g [String]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    owned: Option<Vec<&'g str>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    metadata: Option<SerMetadata<'g>>,
+    cards: Vec<&'g Card>,
+    edges: Vec<Edge<&'g E>>,
+}
+
+#[derive(Deserialize)]
+struct DeGraph<E> {
+    kind: String,
+    #[serde(default)]
+    highlight: Option<String>,
+    #[serde(default)]
+    highlight_chain: Option<Vec<String>>,
+    #[serde(default)]
+    owned: Option<Vec<String>>,
+    #[serde(default)]
+    metadata: Option<Metadata>,
+    cards: Vec<Card>,
+    edges: Vec<Edge<E>>,
+}
+
+impl<K: PodKind> Serialize for PodGraph<K> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        // node indices can have holes after removals, so cards are renumbered
+        let positions = self
+            .g
+            .node_indices()
+            .enumerate()
+            .map(|(i, n)| (n, i))
+            .collect::<HashMap<_, _>>();
+        let edges = self
+            .g
+            .edge_indices()
+            .filter_map(|e| {
+                let (from, to) = self.g.edge_endpoints(e)?;
+                Some(Edge {
+                    from: positions[&from],
+                    to: positions[&to],
+                    label: &self.g[e],
+                })
+            })
+            .collect();
+        SerGraph {
+            kind: K::NAME,
+            highlight: self.highlight(),
+            highlight_chain: self.highlight_chain(),
+            owned: self.owned().map(|o| {
+                let mut names = o.names().collect::<Vec<_>>();
+                names.sort_unstable();
+                names
+            }),
+            metadata: self.metadata().map(|m| SerMetadata {
+                name: m.name.as_deref(),
+                date: m.date.as_deref(),
+                cards: self.g.node_count(),
+                hash: format!("{:016x}", self.content_hash()),
+            }),
+            cards: self.g.node_weights().collect(),
+            edges,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de, K: PodKind + Default> Deserialize<'de> for PodGraph<K> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let graph = DeGraph::<K::Edge>::deserialize(deserializer)?;
+        if graph.kind != K::NAME {
+            return Err(D::Error::custom(format!(
+                "expected a {} graph, found a {} graph",
+                K::NAME,
+                graph.kind
+            )));
+        }
+        let mut g = StableGraph::with_capacity(graph.cards.len(), graph.edges.len());
+        let nodes = graph
+            .cards
+            .into_iter()
+            .map(|c| g.add_node(c))
+            .collect::<Vec<_>>();
+        for Edge { from, to, label } in graph.edges {
+            let (Some(from), Some(to)) = (nodes.get(from), nodes.get(to)) else {
+                return Err(D::Error::custom(format!(
+                    "edge {from} -> {to} points outside of the {} cards",
+                    nodes.len()
+                )));
+            };
+            g.add_edge(*from, *to, label);
+        }
+        let by_cmc = nodes
+            .iter()
+            .fold(HashMap::<_, Vec<_>>::new(), |mut acc, n| {
+                acc.entry(g[*n].cmc).or_default().push(*n);
+                acc
+            });
+        let by_name = nodes.iter().map(|n| (g[*n].name.clone(), *n)).collect();
+        Ok(Self {
+            g,
+            by_cmc,
+            by_name,
+            reachable: Default::default(),
+            kind: K::default(),
+            highlight: graph.highlight,
+            highlight_chain: graph.highlight_chain,
+            owned: graph.owned.map(Collection::from_iter),
+            metadata: graph.metadata,
+        })
+    }
+}
@@ -0,0 +1,470 @@
+//! Where card data comes from.
+
+use std::{
+    collections::HashMap,
+    io,
+    sync::{Arc, OnceLock},
+};
+
+use async_trait::async_trait;
+use scryfall::{
+    card::{Color, Legality},
+    format::Format,
+};
+use serde::{de::DeserializeOwned, Deserialize};
+use tokio::{
+    sync::{Mutex, OnceCell, RwLock},
+    time::{Duration, Instant},
+};
+use tracing::{debug, trace, warn};
+
+use crate::{intern::intern, Card};
+
+mod collection;
+mod mtgjson;
+mod search;
+
+pub use mtgjson::Mtgjson;
+pub use search::search;
+
+/// Resolves card names into [Card]s.
+#[async_trait]
+pub trait CardSource: Send + Sync {
+    /// Looks up the card named `name`, as written in a decklist.
+    async fn resolve(&self, name: &str) -> scryfall::Result<Card>;
+
+    /// Called with every name in a decklist before any of them is
+    /// [resolved](Self::resolve), so they can be looked up in bulk. Does
+    /// nothing by default.
+    async fn prefetch(&self, _names: &[&str]) {}
+}
+
+#[async_trait]
+impl<S: CardSource + ?Sized> CardSource for Arc<S> {
+    async fn resolve(&self, name: &str) -> scryfall::Result<Card> {
+        (**self).resolve(name).await
+    }
+
+    async fn prefetch(&self, names: &[&str]) {
+        (**self).prefetch(names).await
+    }
+}
+
+fn cmc_f32_to_u8(f: f32) -> Option<u8> {
+    let lower = f as u16;
+    let upper = lower + 1;
+    if f > lower as f32 && f < upper as f32 {
+        None
+    } else {
+        lower.try_into().ok()
+    }
+}
+
+/// The rules text of a card, from its own if it has one, from its faces
+/// otherwise.
+fn oracle_text(text: Option<String>, faces: impl IntoIterator<Item = Option<String>>) -> String {
+    text.unwrap_or_else(|| {
+        faces
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .join("\n//\n")
+    })
+}
+
+fn card_from_parts(
+    name: String,
+    cmc: Option<f32>,
+    type_line: Option<String>,
+    legalities: HashMap<Format, Legality>,
+    color_identity: Vec<Color>,
+    oracle_text: String,
+    (power, toughness): (Option<String>, Option<String>),
+) -> scryfall::Result<Card> {
+    let types = type_line
+        .map(|t| t.split(' ').map(intern).collect())
+        .unwrap_or_default();
+    let cmc =
+        cmc.ok_or_else(|| scryfall::Error::Other(format!("{name} doesn't have a mana value")))?;
+    let cmc = cmc_f32_to_u8(cmc)
+        .ok_or_else(|| scryfall::Error::Other(format!("{name} has a fractional mana value")))?;
+    Ok(Card {
+        cmc,
+        name,
+        types,
+        legalities,
+        color_identity,
+        oracle_text,
+        power,
+        toughness,
+        commander: false,
+        tags: Vec::new(),
+        count: 1,
+    })
+}
+
+type Cache = HashMap<String, Card>;
+
+static CACHE: OnceCell<RwLock<Cache>> = OnceCell::const_new();
+
+#[cfg(feature = "fs")]
+const CACHE_PATH: &str = "cache.json";
+#[cfg(feature = "fs")]
+const CACHE_PATH_TMP: &str = "cache.json.tmp";
+
+#[cfg(feature = "fs")]
+async fn cache() -> io::Result<&'static RwLock<HashMap<String, Card>>> {
+    CACHE
+        .get_or_try_init(|| async {
+            let buf = match tokio::fs::read(CACHE_PATH).await {
+                Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Default::default()),
+                r => r,
+            }?;
+            io::Result::Ok(RwLock::new(serde_json::from_slice(&buf)?))
+        })
+        .await
+}
+
+/// Without a filesystem the cache only lives as long as the process.
+#[cfg(not(feature = "fs"))]
+async fn cache() -> io::Result<&'static RwLock<HashMap<String, Card>>> {
+    Ok(CACHE.get_or_init(|| async { Default::default() }).await)
+}
+
+async fn find_in_cache(name: &str) -> io::Result<Option<Card>> {
+    let cache = cache().await?;
+    Ok(cache.read().await.get(name).cloned())
+}
+
+async fn store_in_cache(cards: impl IntoIterator<Item = (String, Card)>) -> io::Result<()> {
+    let cache = cache().await?;
+    let mut cache = cache.write().await;
+    cache.extend(cards);
+    #[cfg(feature = "fs")]
+    {
+        use tokio::io::AsyncWriteExt;
+
+        let mut file = tokio::fs::File::create(CACHE_PATH_TMP).await?;
+        file.write_all(&serde_json::to_vec::<Cache>(&*cache).unwrap())
+            .await?;
+        tokio::fs::rename(CACHE_PATH_TMP, CACHE_PATH).await?;
+    }
+    Ok(())
+}
+
+/// Whether `name` is one of the card's names, rather than a misspelling
+/// that fuzzy matched it.
+#[cfg(feature = "fs")]
+fn names_card(name: &str, card: &Card) -> bool {
+    card.name
+        .split(" // ")
+        .chain([card.name.as_str()])
+        .any(|n| n.eq_ignore_ascii_case(name))
+}
+
+/// Writes every cached card to `path`, in the format of `cache.json`, for
+/// [import_cache] on another machine. Returns how many were written.
+#[cfg(feature = "fs")]
+pub async fn export_cache(path: &std::path::Path) -> io::Result<usize> {
+    let cache = cache().await?.read().await;
+    tokio::fs::write(path, serde_json::to_vec::<Cache>(&cache)?).await?;
+    Ok(cache.len())
+}
+
+/// What [import_cache] did with the cards it read.
+#[cfg(feature = "fs")]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Imported {
+    /// The cards added to the cache.
+    pub added: usize,
+    /// The cards left out because the cache already had them.
+    pub already_cached: usize,
+    /// The cards left out because they're filed under a name that isn't
+    /// theirs, which would make that name resolve to the wrong card.
+    pub rejected: usize,
+}
+
+/// Adds the cards of a file written by [export_cache] to the cache. Cards
+/// already cached are kept as they are, and only cards filed under one of
+/// their own names are taken, so a shared file can't send a name to a
+/// different card. Misspellings the exporter had cached are resolved again.
+#[cfg(feature = "fs")]
+pub async fn import_cache(path: &std::path::Path) -> io::Result<Imported> {
+    let shared = serde_json::from_slice::<Cache>(&tokio::fs::read(path).await?)?;
+    let mut imported = Imported::default();
+    let mut new = Vec::new();
+    {
+        let cache = cache().await?.read().await;
+        for (name, mut card) in shared {
+            // set from decklists, never by a source
+            card.commander = false;
+            card.tags.clear();
+            card.count = 1;
+            if !names_card(&name, &card) {
+                imported.rejected += 1;
+            } else if cache.contains_key(&name) {
+                imported.already_cached += 1;
+            } else {
+                new.push((name, card));
+            }
+        }
+    }
+    imported.added = new.len();
+    store_in_cache(new).await?;
+    Ok(imported)
+}
+
+/// The error Scryfall itself answers with for an unknown card, so offline
+/// sources fail the same way.
+fn not_found(details: String) -> scryfall::Error {
+    scryfall::Error::ScryfallError(scryfall::error::ScryfallError {
+        status: 404,
+        code: "not_found".into(),
+        details,
+        error_type: None,
+        warnings: Vec::new(),
+    })
+}
+
+/// Where Scryfall's API is, unless [set_api_base] points somewhere else.
+pub const DEFAULT_API_BASE: &str = "https://api.scryfall.com";
+
+static API_BASE: std::sync::RwLock<Option<String>> = std::sync::RwLock::new(None);
+
+/// Sends every Scryfall request to `base` instead of [DEFAULT_API_BASE], such
+/// as a caching mirror. Proxies are configured separately, through the usual
+/// `HTTPS_PROXY`, `HTTP_PROXY` and `NO_PROXY` environment variables.
+pub fn set_api_base(base: impl Into<String>) {
+    *API_BASE.write().unwrap_or_else(|e| e.into_inner()) = Some(base.into());
+}
+
+/// The url of a Scryfall endpoint, such as `/cards/search`.
+fn api_url(path: &str) -> String {
+    let base = API_BASE.read().unwrap_or_else(|e| e.into_inner());
+    let base = base.as_deref().unwrap_or(DEFAULT_API_BASE);
+    format!("{}{path}", base.trim_end_matches('/'))
+}
+
+const USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+
+static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+
+/// The client every request goes through, so they share its connections.
+/// It sends [USER_AGENT], which Scryfall asks for.
+pub(crate) fn client() -> &'static reqwest::Client {
+    CLIENT.get_or_init(|| {
+        reqwest::Client::builder()
+            .user_agent(USER_AGENT)
+            .build()
+            .expect("the tls backend initializes")
+    })
+}
+
+/// Sends a GET to a Scryfall endpoint, turning error responses into
+/// [scryfall::Error::ScryfallError]s.
+async fn get<T: DeserializeOwned>(url: &str) -> scryfall::Result<T> {
+    let response = client().get(url).send().await?;
+    if response.status().is_success() {
+        Ok(response.json().await?)
+    } else {
+        Err(scryfall::Error::ScryfallError(response.json().await?))
+    }
+}
+
+/// Scryfall asks for 50 to 100 milliseconds between requests.
+const REQUEST_INTERVAL: Duration = Duration::from_millis(100);
+
+static NEXT_REQUEST: Mutex<Option<Instant>> = Mutex::const_new(None);
+
+/// Waits until another request can be sent to Scryfall without going over
+/// its rate limit.
+async fn throttle() {
+    let mut next = NEXT_REQUEST.lock().await;
+    let now = Instant::now();
+    if let Some(next) = *next {
+        if next > now {
+            trace!("throttling for {:?}", next - now);
+            tokio::time::sleep_until(next).await;
+        }
+    }
+    *next = Some(next.map_or(now, |next| next.max(now)) + REQUEST_INTERVAL);
+}
+
+/// Fuzzy lookups through the Scryfall API, remembered in `cache.json`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Scryfall;
+
+#[async_trait]
+impl CardSource for Scryfall {
+    #[tracing::instrument(level = "debug", skip(self))]
+    async fn resolve(&self, name: &str) -> scryfall::Result<Card> {
+        match find_in_cache(name).await {
+            Ok(Some(card)) => return Ok(card),
+            Err(e) if e.kind() != io::ErrorKind::NotFound => {
+                warn!("failed to fetch from cache: {e:?}");
+            }
+            _ => {
+                debug!("cache miss");
+            }
+        }
+        throttle().await;
+        let url = url::Url::parse_with_params(&api_url("/cards/named"), [("fuzzy", name)])?;
+        let card = Card::try_from(get::<RawCard>(url.as_str()).await?)?;
+        if let Err(e) = store_in_cache([(name.to_owned(), card.clone())]).await {
+            warn!("failed to store in cache: {e:?}");
+        }
+        Ok(card)
+    }
+
+    /// Looks up the names that aren't cached yet through Scryfall's
+    /// collection endpoint, 75 at a time. Names it can't match exactly are
+    /// left for [resolve](Self::resolve) to fuzzy match.
+    #[tracing::instrument(level = "debug", skip_all, fields(names = names.len()))]
+    async fn prefetch(&self, names: &[&str]) {
+        let mut missing = Vec::with_capacity(names.len());
+        match cache().await {
+            Ok(cache) => {
+                let cache = cache.read().await;
+                missing.extend(names.iter().filter(|n| !cache.contains_key(**n)));
+            }
+            Err(e) => warn!("failed to fetch from cache: {e:?}"),
+        }
+        missing.sort_unstable();
+        missing.dedup();
+        for batch in missing.chunks(collection::BATCH_SIZE) {
+            throttle().await;
+            let cards = match collection::fetch(batch).await {
+                Ok(cards) => cards,
+                Err(e) => {
+                    warn!("failed to fetch a batch of {} cards: {e}", batch.len());
+                    continue;
+                }
+            };
+            debug!("batch matched {} of {} cards", cards.len(), batch.len());
+            if let Err(e) = store_in_cache(cards).await {
+                warn!("failed to store in cache: {e:?}");
+            }
+        }
+    }
+}
+
+/// Only the cards already in `cache.json`. Never touches the network.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CacheOnly;
+
+#[async_trait]
+impl CardSource for CacheOnly {
+    async fn resolve(&self, name: &str) -> scryfall::Result<Card> {
+        find_in_cache(name)
+            .await?
+            .ok_or_else(|| not_found(format!("{name} is not in the cache")))
+    }
+}
+
+/// The subset of a Scryfall card that is needed. Legalities are kept as
+/// strings so formats this version of the scryfall crate doesn't know about
+/// don't fail a whole bulk data file or batch of cards.
+#[derive(Deserialize)]
+struct RawCard {
+    name: String,
+    cmc: Option<f32>,
+    type_line: Option<String>,
+    #[serde(default)]
+    legalities: HashMap<String, Legality>,
+    #[serde(default)]
+    color_identity: Vec<Color>,
+    oracle_text: Option<String>,
+    power: Option<String>,
+    toughness: Option<String>,
+    #[serde(default)]
+    card_faces: Vec<RawFace>,
+}
+
+#[derive(Deserialize)]
+struct RawFace {
+    oracle_text: Option<String>,
+    power: Option<String>,
+    toughness: Option<String>,
+}
+
+/// Makes the card findable by its lowercased full name and the names of each
+/// of its faces.
+fn index_by_name(cards: &mut HashMap<String, Card>, card: Card) {
+    for face in card.name.split(" // ") {
+        cards.insert(face.to_lowercase(), card.clone());
+    }
+    cards.insert(card.name.to_lowercase(), card);
+}
+
+/// The format named `name`, if this version of the scryfall crate knows it.
+fn known_format(name: String) -> Option<Format> {
+    serde_json::from_value(serde_json::Value::String(name)).ok()
+}
+
+impl TryFrom<RawCard> for Card {
+    type Error = scryfall::Error;
+
+    fn try_from(card: RawCard) -> scryfall::Result<Self> {
+        let legalities = card
+            .legalities
+            .into_iter()
+            .filter_map(|(format, legality)| known_format(format).map(|f| (f, legality)))
+            .collect();
+        let front = card.card_faces.first();
+        let stats = (
+            card.power.or_else(|| front.and_then(|f| f.power.clone())),
+            card.toughness
+                .or_else(|| front.and_then(|f| f.toughness.clone())),
+        );
+        card_from_parts(
+            card.name,
+            card.cmc,
+            card.type_line,
+            legalities,
+            card.color_identity,
+            oracle_text(
+                card.oracle_text,
+                card.card_faces.into_iter().map(|f| f.oracle_text),
+            ),
+            stats,
+        )
+    }
+}
+
+/// A local Scryfall [bulk data](https://scryfall.com/docs/api/bulk-data)
+/// file, such as "Oracle Cards", matched by exact (case insensitive) name.
+#[derive(Debug, Default)]
+pub struct BulkData {
+    cards: HashMap<String, Card>,
+}
+
+impl BulkData {
+    /// Loads a bulk data file, a json array of cards.
+    #[cfg(feature = "fs")]
+    pub async fn load(path: impl AsRef<std::path::Path>) -> io::Result<Self> {
+        Ok(Self::from_slice(&tokio::fs::read(path).await?)?)
+    }
+
+    /// Parses bulk data that is already in memory.
+    pub fn from_slice(buf: &[u8]) -> serde_json::Result<Self> {
+        let bulk = serde_json::from_slice::<Vec<RawCard>>(buf)?;
+        let mut cards = HashMap::with_capacity(bulk.len());
+        for card in bulk {
+            let Ok(card) = Card::try_from(card) else {
+                continue;
+            };
+            index_by_name(&mut cards, card);
+        }
+        Ok(Self { cards })
+    }
+}
+
+#[async_trait]
+impl CardSource for BulkData {
+    async fn resolve(&self, name: &str) -> scryfall::Result<Card> {
+        self.cards
+            .get(&name.to_lowercase())
+            .cloned()
+            .ok_or_else(|| not_found(format!("{name} is not in the bulk data")))
+    }
+}
@@ -0,0 +1,56 @@
+//! Scryfall's `/cards/collection` endpoint, which looks up to
+//! [BATCH_SIZE] cards in a single request.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::{api_url, client, index_by_name, RawCard};
+use crate::Card;
+
+/// The most identifiers Scryfall accepts per request.
+pub(super) const BATCH_SIZE: usize = 75;
+
+#[derive(Serialize)]
+struct Identifier<'n> {
+    name: &'n str,
+}
+
+#[derive(Serialize)]
+struct Request<'n> {
+    identifiers: Vec<Identifier<'n>>,
+}
+
+#[derive(Deserialize)]
+struct Response {
+    data: Vec<RawCard>,
+}
+
+/// Looks up `names` by exact name, returning the cards that were found keyed
+/// by the name they were asked for.
+pub(super) async fn fetch(names: &[&str]) -> scryfall::Result<Vec<(String, Card)>> {
+    let response = client()
+        .post(api_url("/cards/collection"))
+        .json(&Request {
+            identifiers: names.iter().map(|name| Identifier { name }).collect(),
+        })
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<Response>()
+        .await?;
+    let mut by_name = HashMap::new();
+    for card in response.data {
+        let Ok(card) = Card::try_from(card) else {
+            continue;
+        };
+        index_by_name(&mut by_name, card);
+    }
+    Ok(names
+        .iter()
+        .filter_map(|name| {
+            let card = by_name.get(&name.to_lowercase())?;
+            Some((name.to_string(), card.clone()))
+        })
+        .collect())
+}
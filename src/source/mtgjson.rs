@@ -0,0 +1,171 @@
+//! Local [MTGJSON](https://mtgjson.com) files, `AtomicCards.json` or
+//! `AllPrintings.json`.
+
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use scryfall::card::Color;
+use serde::Deserialize;
+
+use super::{card_from_parts, index_by_name, known_format, not_found, oracle_text, CardSource};
+use crate::Card;
+
+/// One face of one printing. Only the fields both files share.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Face {
+    name: String,
+    face_name: Option<String>,
+    /// `a` for the front face, `b` for the back, and so on.
+    side: Option<String>,
+    mana_value: Option<f32>,
+    #[serde(rename = "type")]
+    type_line: Option<String>,
+    #[serde(default)]
+    legalities: HashMap<String, String>,
+    #[serde(default)]
+    color_identity: Vec<Color>,
+    text: Option<String>,
+    power: Option<String>,
+    toughness: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Set {
+    cards: Vec<Face>,
+}
+
+/// `AtomicCards` maps names to faces, `AllPrintings` maps set codes to sets.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Entry {
+    Faces(Vec<Face>),
+    Set(Set),
+}
+
+#[derive(Deserialize)]
+struct File {
+    data: HashMap<String, Entry>,
+}
+
+/// A card's faces, merged into one type line the way Scryfall writes it, and
+/// one rules text.
+struct Merged {
+    first: Face,
+    faces: Vec<String>,
+    type_lines: Vec<(Option<String>, String)>,
+    texts: Vec<(Option<String>, String)>,
+}
+
+impl Merged {
+    fn add(&mut self, face: Face) {
+        let Some(name) = face.face_name else {
+            return;
+        };
+        if !self.faces.contains(&name) {
+            self.faces.push(name);
+            self.type_lines
+                .extend(face.type_line.map(|t| (face.side.clone(), t)));
+            self.texts.extend(face.text.map(|t| (face.side, t)));
+        }
+    }
+}
+
+/// A local MTGJSON file, matched by exact (case insensitive) name like
+/// [BulkData](super::BulkData).
+#[derive(Debug, Default)]
+pub struct Mtgjson {
+    cards: HashMap<String, Card>,
+}
+
+impl Mtgjson {
+    /// Loads an `AtomicCards.json` or `AllPrintings.json` file.
+    #[cfg(feature = "fs")]
+    pub async fn load(path: impl AsRef<std::path::Path>) -> std::io::Result<Self> {
+        Ok(Self::from_slice(&tokio::fs::read(path).await?)?)
+    }
+
+    /// Parses an MTGJSON file that is already in memory.
+    pub fn from_slice(buf: &[u8]) -> serde_json::Result<Self> {
+        let file = serde_json::from_slice::<File>(buf)?;
+        let faces = file.data.into_values().flat_map(|entry| match entry {
+            Entry::Faces(faces) => faces,
+            Entry::Set(set) => set.cards,
+        });
+        let mut merged = HashMap::<String, Merged>::new();
+        for face in faces {
+            match merged.get_mut(&face.name) {
+                Some(card) => card.add(face),
+                None => {
+                    let name = face.name.clone();
+                    let card = Merged {
+                        faces: face.face_name.iter().cloned().collect(),
+                        type_lines: face
+                            .type_line
+                            .iter()
+                            .map(|t| (face.side.clone(), t.clone()))
+                            .collect(),
+                        texts: face
+                            .text
+                            .iter()
+                            .map(|t| (face.side.clone(), t.clone()))
+                            .collect(),
+                        first: face,
+                    };
+                    merged.insert(name, card);
+                }
+            }
+        }
+        let mut cards = HashMap::with_capacity(merged.len());
+        for Merged {
+            first,
+            mut type_lines,
+            mut texts,
+            ..
+        } in merged.into_values()
+        {
+            let legalities = first
+                .legalities
+                .into_iter()
+                .filter_map(|(format, legality)| {
+                    let legality = legality.to_lowercase().replace(' ', "_");
+                    Some((
+                        known_format(format)?,
+                        serde_json::from_value(serde_json::Value::String(legality)).ok()?,
+                    ))
+                })
+                .collect();
+            type_lines.sort_by(|(a, _), (b, _)| a.cmp(b));
+            let type_line = (!type_lines.is_empty()).then(|| {
+                let faces = type_lines.into_iter().map(|(_, t)| t);
+                faces.collect::<Vec<_>>().join(" // ")
+            });
+            texts.sort_by(|(a, _), (b, _)| a.cmp(b));
+            let texts = texts.into_iter().map(|(_, t)| Some(t));
+            let card = card_from_parts(
+                first.name,
+                first.mana_value,
+                type_line,
+                legalities,
+                first.color_identity,
+                oracle_text(None, texts),
+                (first.power, first.toughness),
+            );
+            let Ok(card) = card else {
+                continue;
+            };
+            index_by_name(&mut cards, card);
+        }
+        Ok(Self { cards })
+    }
+}
+
+#[async_trait]
+impl CardSource for Mtgjson {
+    async fn resolve(&self, name: &str) -> scryfall::Result<Card> {
+        self.cards
+            .get(&name.to_lowercase())
+            .cloned()
+            .ok_or_else(|| not_found(format!("{name} is not in the mtgjson file")))
+    }
+}
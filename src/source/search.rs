@@ -0,0 +1,48 @@
+//! Scryfall's `/cards/search` endpoint, which pages through every card
+//! matching a query in Scryfall's search syntax.
+
+use futures::{stream, Stream, StreamExt};
+use serde::Deserialize;
+
+use super::{api_url, get, throttle, RawCard};
+use crate::Card;
+
+#[derive(Deserialize)]
+struct Page {
+    data: Vec<RawCard>,
+    next_page: Option<String>,
+}
+
+async fn fetch(url: &str) -> scryfall::Result<Page> {
+    throttle().await;
+    get(url).await
+}
+
+/// Every card matching `query`, such as `t:creature id<=gw cmc<=6`, fetched a
+/// page at a time as the stream is polled. Cards without a whole mana value
+/// are skipped. The stream ends after the first error.
+pub fn search(query: &str) -> impl Stream<Item = scryfall::Result<Card>> {
+    let (first, error) =
+        match url::Url::parse_with_params(&api_url("/cards/search"), [("q", query)]) {
+            Ok(url) => (Some(url.to_string()), None),
+            Err(e) => (None, Some(Err(e.into()))),
+        };
+    let pages = stream::unfold(first, |url| async move {
+        let page = fetch(&url?).await;
+        Some(match page {
+            Ok(page) => (Ok(page.data), page.next_page),
+            Err(e) => (Err(e), None),
+        })
+    })
+    .flat_map(|page| {
+        stream::iter(match page {
+            Ok(cards) => cards
+                .into_iter()
+                .filter_map(|c| Card::try_from(c).ok())
+                .map(Ok)
+                .collect(),
+            Err(e) => vec![Err(e)],
+        })
+    });
+    stream::iter(error).chain(pages)
+}